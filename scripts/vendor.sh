@@ -0,0 +1,32 @@
+#!/bin/sh
+# Vendors every crate a toolchain profile can build contracts with, so builds run fully offline
+# Usage: scripts/vendor.sh <template_dir> <vendor_dir>
+# Then set "vendor_dir": "<vendor_dir>" in the profile of toolchains.json
+set -e
+
+if [ "$#" -ne 2 ]; then
+    echo "Usage: $0 <template_dir> <vendor_dir>" >&2
+    exit 1
+fi
+
+template_dir=$1
+vendor_dir=$2
+# Every openbrush feature of the feature catalog (FEATURES in src/utils/sanity_check.rs), so the lock
+# covers all feature sets. A unit test checks this list against the catalog
+features='"psp22", "psp34", "psp37", "pausable", "ownable", "access_control", "timelock_controller", "reentrancy_guard", "upgradeable", "proxy", "diamond"'
+
+crate_dir=$(mktemp -d)
+trap 'rm -rf "$crate_dir"' EXIT
+
+sed "s/{{openbrush_features}}/$features/" "$template_dir/template-Cargo.toml" > "$crate_dir/Cargo.toml"
+cp "$template_dir/template-lib.rs" "$crate_dir/lib.rs"
+if [ -f "$template_dir/Cargo.lock" ]; then
+    cp "$template_dir/Cargo.lock" "$crate_dir/Cargo.lock"
+fi
+
+mkdir -p "$vendor_dir"
+cargo vendor --versioned-dirs --manifest-path "$crate_dir/Cargo.toml" "$vendor_dir" > "$crate_dir/config.toml"
+cp "$crate_dir/config.toml" "$vendor_dir/config.toml"
+cp "$crate_dir/Cargo.lock" "$vendor_dir/Cargo.lock"
+
+echo "Vendored dependencies of $template_dir into $vendor_dir"
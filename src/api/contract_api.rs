@@ -1,117 +1,345 @@
-use std::sync::mpsc::channel;
+// Handlers answer errors with the whole ServerResponse, so their Err variant is as large as the
+// Ok one. It is returned once per request, boxing it would only add an allocation
+#![allow(clippy::result_large_err)]
+
 use std::sync::Arc;
 
-use crate::utils::compilation_queue::CompilationRequest;
-use crate::utils::sanity_check::check_address;
+use crate::utils::build_key::build_key;
+use crate::utils::codegen::{check_generate_message, generated_features, render_contract};
+use crate::utils::feature_rules::FeatureRules;
+use crate::utils::guards::{AdminAccess, AsyncCompilation};
+use crate::utils::sanity_check::{check_address, check_features, check_priority, check_toolchain};
+use crate::utils::toolchains::{ToolchainProfile, Toolchains};
 use crate::{
     models::{
         api_models::{
-            DeployMessage, GetDeploymentsMessage, ServerResponse, UpdateDeployMessage,
-            WizardMessage,
+            ApiError, DeployMessage, Diagnostic, FileDownload, GenerateMessage, GeneratedContract,
+            GetDeploymentsMessage, QueueSummary, ServerError, ServerResponse, TestReport,
+            UpdateDeployMessage, WizardMessage,
         },
-        db_models::{Contract, Deployment},
+        db_models::{CompilationJob, Contract, Deployment, JobKind, JobStatus},
     },
     repository::mongodb_repo::MongoRepo,
-    utils::compilation_queue::CompilationQueue,
+    utils::compilation_queue::{CancelError, CompilationQueue, QueueError, QueuedJob},
     utils::sanity_check::sanity_check_wizard_message,
 };
 use log::{debug, error, info};
 use rocket::response::status::Custom;
-use rocket::{http::Status, serde::json::Json, State};
+use rocket::{
+    http::{ContentType, Status},
+    serde::json::Json,
+    State,
+};
 use sha2::{Digest, Sha256};
 
 // /contract endpoint for obtaining a new contract compilation
-#[post("/contract", data = "<wizard_message>")]
+#[post("/contract", data = "<wizard_message>", rank = 2)]
 pub fn fetch_or_compile_contract(
     compilation_queue: &State<Arc<CompilationQueue>>,
+    toolchains: &State<Arc<Toolchains>>,
+    feature_rules: &State<Arc<FeatureRules>>,
     db: &State<MongoRepo>,
+    admin: AdminAccess,
     wizard_message: Json<WizardMessage>,
-) -> Result<Json<ServerResponse<Contract>>, Custom<Json<ServerResponse<Contract>>>> {
+) -> Result<Json<ServerResponse<Contract>>, ApiError<Contract>> {
+    let contract = compile_wizard_message(
+        compilation_queue,
+        toolchains,
+        feature_rules,
+        db,
+        admin,
+        wizard_message,
+    )?;
+
+    Ok(Json(ServerResponse::new_valid(contract)))
+}
+
+// /contract?async=true endpoint for queueing a contract compilation without waiting for it
+#[post("/contract", data = "<wizard_message>", rank = 1)]
+pub fn queue_contract_compilation(
+    _async_compilation: AsyncCompilation,
+    compilation_queue: &State<Arc<CompilationQueue>>,
+    toolchains: &State<Arc<Toolchains>>,
+    feature_rules: &State<Arc<FeatureRules>>,
+    db: &State<MongoRepo>,
+    admin: AdminAccess,
+    wizard_message: Json<WizardMessage>,
+) -> Result<Json<ServerResponse<CompilationJob>>, ApiError<CompilationJob>> {
     // Checking input data
-    sanity_check_wizard_message(&wizard_message)?;
+    let profile = check_toolchain(toolchains, &wizard_message.toolchain)?;
+    sanity_check_wizard_message(&wizard_message, profile, feature_rules)?;
+    check_priority(&wizard_message.priority, admin.0)?;
 
     // Hashing the contract code to create an unique identifier
     let code_hash_str = hash_code(&wizard_message.code);
     debug!(target: "compiler", "hash_code completed");
 
-    // Check if contract already exists in DB
-    let contract_on_db = db.get_contract_by_hash(&code_hash_str);
-    debug!(target: "compiler", "get_contract_by_hash completed");
+    // The build key identifies the artifact, the same code can be built with other features or toolchains
+    let build_key = resolve_build_key(profile, &wizard_message, &code_hash_str)?;
+
+    let job_res = match find_stored_contract(db, &build_key) {
+        // If contract already exists in DB, the job is created as already finished
+        Some(contract) => compilation_queue
+            .add_finished_job(contract)
+            .map(|job_id| QueuedJob {
+                job_id,
+                attached: false,
+                release_token: None,
+            })
+            .map_err(QueueError::Internal),
+        // The client polls the job, so it keeps the job alive even if a blocking request on it gives up
+        // The queue stores the contract once it is built
+        None => compilation_queue.add_polled_request(
+            wizard_message.into_inner(),
+            code_hash_str.clone(),
+            build_key,
+        ),
+    };
 
-    // If contract already exists in DB, return it
-    match contract_on_db {
-        Ok(contract) => match contract {
-            Some(mut contract) => {
-                info!(target: "compiler", "Contract existing in the db with id: {:?}", &contract.id);
-                contract.id = None;
-                return Ok(Json(ServerResponse::new_valid(contract)));
-            }
-            None => (),
-        },
-        Err(_) => {
-            error!(target: "compiler", "Error getting contract from db");
+    let queued_job = job_res.map_err(|error| {
+        error!(target: "compiler", "The contract {} was not queued: {:?}", &code_hash_str, error);
+        queue_error_response(error)
+    })?;
+
+    match compilation_queue.get_job(&queued_job.job_id) {
+        Some(mut job) => {
+            info!(target: "compiler", "Job {} created for contract {}", &job.id, &code_hash_str);
+            // Only this client gets the token, the job is returned by GET /jobs without it
+            job.release_token = queued_job.release_token;
+            Ok(Json(ServerResponse::new_valid(job)))
+        }
+        None => {
+            error!(target: "compiler", "There was an error queueing the contract {}", &code_hash_str);
+            Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::new_error(String::from(
+                    "Error queueing contract.",
+                ))),
+            )
+            .into())
         }
     }
+}
 
-    // If contract does not exist in DB, create it
+// /contract/check endpoint for type-checking a contract without building the wasm
+#[post("/contract/check", data = "<wizard_message>")]
+pub fn check_contract(
+    compilation_queue: &State<Arc<CompilationQueue>>,
+    toolchains: &State<Arc<Toolchains>>,
+    feature_rules: &State<Arc<FeatureRules>>,
+    admin: AdminAccess,
+    wizard_message: Json<WizardMessage>,
+) -> Result<Json<ServerResponse<Vec<Diagnostic>>>, ApiError<Vec<Diagnostic>>> {
+    // Checks go through the same queue as the builds, but are taken first
+    let job = run_queued_job(
+        compilation_queue,
+        toolchains,
+        feature_rules,
+        admin,
+        wizard_message,
+        JobKind::Check,
+    )?;
+
+    match (job.status, job.error) {
+        (JobStatus::Succeeded, _) => Ok(Json(ServerResponse::new_valid(
+            job.diagnostics.unwrap_or_default(),
+        ))),
+        // If the check failed, return the error along with the compiler diagnostics
+        (_, error) => {
+            info!(target: "compiler", "Check failed for contract {}", &job.code_id);
+            Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::from_error(error.unwrap_or(
+                    ServerError::new(String::from("Error checking contract.")),
+                ))),
+            )
+            .into())
+        }
+    }
+}
 
-    // Creating a channel to communicate with the compilation thread
-    let (tx, rx) = channel::<Result<Contract, String>>();
+// /contract/test endpoint for running the #[ink::test] unit tests of a contract
+#[post("/contract/test", data = "<wizard_message>")]
+pub fn test_contract(
+    compilation_queue: &State<Arc<CompilationQueue>>,
+    toolchains: &State<Arc<Toolchains>>,
+    feature_rules: &State<Arc<FeatureRules>>,
+    admin: AdminAccess,
+    wizard_message: Json<WizardMessage>,
+) -> Result<Json<ServerResponse<TestReport>>, ApiError<TestReport>> {
+    let job = run_queued_job(
+        compilation_queue,
+        toolchains,
+        feature_rules,
+        admin,
+        wizard_message,
+        JobKind::Test,
+    )?;
+
+    match (job.status, job.test_report, job.error) {
+        // Failing tests are reported in the test report, not as an error
+        (JobStatus::Succeeded, Some(test_report), _) => {
+            Ok(Json(ServerResponse::new_valid(test_report)))
+        }
+        // If the tests didn't compile, return the error along with the compiler diagnostics
+        (_, _, error) => {
+            info!(target: "compiler", "Tests could not run for contract {}", &job.code_id);
+            Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::from_error(error.unwrap_or(
+                    ServerError::new(String::from("Error running tests.")),
+                ))),
+            )
+            .into())
+        }
+    }
+}
 
-    // Sending the compilation request to the compilation thread
-    let compilation_request = CompilationRequest {
-        wizard_message: wizard_message.into_inner(),
-        code_id: code_hash_str.clone(),
-        tx: tx.clone(),
-    };
-    compilation_queue.add_request(compilation_request);
+// /generate endpoint for rendering a contract from the wizard parameters, compiling it when requested
+#[post("/generate", data = "<generate_message>")]
+pub fn generate_contract(
+    compilation_queue: &State<Arc<CompilationQueue>>,
+    toolchains: &State<Arc<Toolchains>>,
+    feature_rules: &State<Arc<FeatureRules>>,
+    db: &State<MongoRepo>,
+    admin: AdminAccess,
+    generate_message: Json<GenerateMessage>,
+) -> Result<Json<ServerResponse<GeneratedContract>>, ApiError<GeneratedContract>> {
+    // Checking input data
+    let check_res = check_address(&generate_message.address)
+        .and_then(|_| check_generate_message(&generate_message));
+    if let Err(msg) = check_res {
+        return Err(Custom(
+            Status::InternalServerError,
+            Json(ServerResponse::new_error(msg)),
+        )
+        .into());
+    }
 
-    // Waiting for the compilation thread to finish
-    let comp_msg = rx.recv();
+    let features = generated_features(&generate_message);
+    check_features(&features, feature_rules)?;
 
-    if comp_msg.is_err() {
-        error!(target: "compiler", "Error receiving compilation result from channel");
+    // The templates are written for openbrush, so the contract can only be built by its profiles
+    let profile = check_toolchain(toolchains, &generate_message.toolchain)?;
+    if profile.openbrush_version.is_none() {
+        error!(target: "compiler", "Contract generation requested for toolchain {}", &profile.name);
         return Err(Custom(
             Status::InternalServerError,
-            Json(ServerResponse::new_error(
-                "Error compiling contract".to_string(),
-            )),
-        ));
+            Json(ServerResponse::new_error(String::from(
+                "Contracts are only generated for openbrush toolchains.",
+            ))),
+        )
+        .into());
     }
 
-    // Getting the compilation result
-    let contract = comp_msg.expect("This will never panic because we checked for errors before");
+    let code = render_contract(&generate_message);
+    debug!(target: "compiler", "render_contract completed");
+
+    let generate_message = generate_message.into_inner();
+    let contract = if generate_message.compile {
+        let wizard_message = WizardMessage {
+            address: generate_message.address,
+            code: code.clone(),
+            features: features.clone(),
+            toolchain: generate_message.toolchain,
+            build_options: generate_message.build_options,
+            lint: false,
+            priority: generate_message.priority,
+        };
+
+        Some(compile_wizard_message(
+            compilation_queue,
+            toolchains,
+            feature_rules,
+            db,
+            admin,
+            Json(wizard_message),
+        )?)
+    } else {
+        None
+    };
 
-    // Checking if compilation was successful
-    match contract {
-        Ok(contract_unwrapped) => {
-            let contract_save_result = db.create_contract(&contract_unwrapped);
-            info!(target: "compiler",
-                "Contract {} successfully compiled", &contract_unwrapped.code_id
-            );
-
-            // Store contract compiled
-            match contract_save_result {
-                Ok(_) => {
-                    info!(target: "compiler", "Contract {} saved in the database", &contract_unwrapped.code_id);
-                }
-                Err(_) => {
-                    error!(target: "compiler", "There was an error saving the contract {} in the database", &contract_unwrapped.code_id);
-                }
-            };
-
-            return Ok(Json(ServerResponse::new_valid(contract_unwrapped)));
-        }
-        // If compilation failed, return the error
-        Err(error_msg) => {
-            error!(target: "compiler", "There was an error saving the contract {} in the database", &code_hash_str);
+    Ok(Json(ServerResponse::new_valid(GeneratedContract {
+        code,
+        features,
+        contract,
+    })))
+}
 
-            return Err(Custom(
-                Status::InternalServerError,
-                Json(ServerResponse::new_error(error_msg)),
-            ));
+// /queue endpoint with the depth of the queue, the running builds and the average latencies
+#[get("/queue")]
+pub fn get_queue(
+    compilation_queue: &State<Arc<CompilationQueue>>,
+) -> Json<ServerResponse<QueueSummary>> {
+    Json(ServerResponse::new_valid(compilation_queue.summary()))
+}
+
+// /jobs endpoint for polling the status of a compilation job
+#[get("/jobs/<id>")]
+pub fn get_job(
+    compilation_queue: &State<Arc<CompilationQueue>>,
+    id: String,
+) -> Result<Json<ServerResponse<CompilationJob>>, Custom<Json<ServerResponse<CompilationJob>>>> {
+    match compilation_queue.get_job(&id) {
+        Some(job) => Ok(Json(ServerResponse::new_valid(job))),
+        None => {
+            info!(target: "compiler", "Job was not found for {}", &id);
+            Err(Custom(
+                Status::NotFound,
+                Json(ServerResponse::new_error(format!(
+                    "No job found for ID {}",
+                    &id
+                ))),
+            ))
         }
+    }
+}
+
+// /jobs endpoint for giving up on a queued or compiling job with the release token of the async request
+// The job is only cancelled once nobody else waits for it or polls it, admins cancel it right away
+#[delete("/jobs/<id>?<release_token>")]
+pub fn cancel_job(
+    compilation_queue: &State<Arc<CompilationQueue>>,
+    admin: AdminAccess,
+    id: String,
+    release_token: Option<String>,
+) -> Result<Json<ServerResponse<CompilationJob>>, Custom<Json<ServerResponse<CompilationJob>>>> {
+    let cancel_res = match (admin.0, release_token) {
+        (true, _) => compilation_queue.cancel_job(&id),
+        (false, Some(release_token)) => compilation_queue.release_job(&id, &release_token),
+        (false, None) => Err(CancelError::InvalidToken),
     };
+
+    match cancel_res {
+        Ok(job) => Ok(Json(ServerResponse::new_valid(job))),
+        Err(CancelError::NotFound) => {
+            info!(target: "compiler", "Job was not found for {}", &id);
+            Err(Custom(
+                Status::NotFound,
+                Json(ServerResponse::new_error(format!(
+                    "No job found for ID {}",
+                    &id
+                ))),
+            ))
+        }
+        Err(CancelError::Finished) => Err(Custom(
+            Status::Conflict,
+            Json(ServerResponse::new_error(format!(
+                "Job {} is already finished.",
+                &id
+            ))),
+        )),
+        Err(CancelError::InvalidToken) => Err(Custom(
+            Status::Forbidden,
+            Json(ServerResponse::new_error(String::from(
+                "Invalid release token.",
+            ))),
+        )),
+        Err(CancelError::Internal(error_msg)) => Err(Custom(
+            Status::InternalServerError,
+            Json(ServerResponse::new_error(error_msg)),
+        )),
+    }
 }
 
 // /deployments endpoint for storing a new deployment
@@ -288,8 +516,12 @@ pub fn get_contract(
                 contract = Contract {
                     id: None,
                     code_id: contract.code_id,
+                    build_key: contract.build_key,
                     metadata: contract.metadata,
-                    wasm: vec![], // Empty wasm
+                    wasm: vec![],          // Empty wasm
+                    bundle: String::new(), // The bundle embeds the wasm too
+                    cargo_lock: contract.cargo_lock,
+                    lints: contract.lints,
                 };
             }
 
@@ -307,6 +539,66 @@ pub fn get_contract(
     }
 }
 
+// /contract/bundle endpoint for downloading the .contract bundle of a compiled contract
+#[get("/contract/bundle?<code_id>&<build_key>")]
+pub fn get_contract_bundle(
+    db: &State<MongoRepo>,
+    code_id: Option<String>,
+    build_key: Option<String>,
+) -> Result<FileDownload, Custom<Json<ServerResponse<String>>>> {
+    let contract = find_contract_to_download(db, code_id, build_key)?;
+
+    // Contracts stored before bundles were kept don't have one
+    if contract.bundle.is_empty() {
+        return Err(Custom(
+            Status::NotFound,
+            Json(ServerResponse::new_error(String::from(
+                "Contract bundle not available.",
+            ))),
+        ));
+    }
+
+    Ok(FileDownload::new(
+        "compiled_contract.contract",
+        ContentType::JSON,
+        contract.bundle,
+    ))
+}
+
+// /contract/lock endpoint for downloading the Cargo.lock a contract was built with
+#[get("/contract/lock?<code_id>&<build_key>")]
+pub fn get_contract_lock(
+    db: &State<MongoRepo>,
+    code_id: Option<String>,
+    build_key: Option<String>,
+) -> Result<FileDownload, Custom<Json<ServerResponse<String>>>> {
+    let contract = find_contract_to_download(db, code_id, build_key)?;
+
+    // Contracts stored before lock files were kept don't have one
+    if contract.cargo_lock.is_empty() {
+        return Err(Custom(
+            Status::NotFound,
+            Json(ServerResponse::new_error(String::from(
+                "Cargo.lock not available.",
+            ))),
+        ));
+    }
+
+    Ok(FileDownload::new(
+        "Cargo.lock",
+        ContentType::Plain,
+        contract.cargo_lock,
+    ))
+}
+
+// /toolchains endpoint for listing the toolchain profiles installed
+#[get("/toolchains")]
+pub fn get_toolchains(
+    toolchains: &State<Arc<Toolchains>>,
+) -> Json<ServerResponse<Vec<ToolchainProfile>>> {
+    Json(ServerResponse::new_valid(toolchains.profiles.clone()))
+}
+
 // Endpoint for fetching api version
 #[get("/version")]
 pub fn get_version() -> Json<ServerResponse<String>> {
@@ -315,6 +607,222 @@ pub fn get_version() -> Json<ServerResponse<String>> {
     ))))
 }
 
+// This function looks for a contract already built with the same build key in the db
+fn find_stored_contract(db: &MongoRepo, build_key: &str) -> Option<Contract> {
+    let contract_on_db = db.get_contract_by_build_key(build_key);
+    debug!(target: "compiler", "get_contract_by_build_key completed");
+
+    match contract_on_db {
+        Ok(Some(mut contract)) => {
+            info!(target: "compiler", "Contract existing in the db with id: {:?}", &contract.id);
+            contract.id = None;
+            Some(contract)
+        }
+        Ok(None) => None,
+        Err(_) => {
+            error!(target: "compiler", "Error getting contract from db");
+            None
+        }
+    }
+}
+
+// This function fetches the contract a file is downloaded from, by build key or code_id
+fn find_contract_to_download(
+    db: &MongoRepo,
+    code_id: Option<String>,
+    build_key: Option<String>,
+) -> Result<Contract, Custom<Json<ServerResponse<String>>>> {
+    let db_result = match (&build_key, &code_id) {
+        (Some(build_key), _) => db.get_contract_by_build_key(build_key),
+        (None, Some(code_id)) => db.get_contract_by_hash(code_id),
+        (None, None) => {
+            return Err(Custom(
+                Status::BadRequest,
+                Json(ServerResponse::new_error(String::from(
+                    "code_id or build_key is required.",
+                ))),
+            ))
+        }
+    };
+
+    match db_result {
+        Ok(Some(contract)) => Ok(contract),
+        Ok(None) => {
+            info!(target: "compiler", "Contract was not found for {:?} {:?}", &code_id, &build_key);
+            Err(Custom(
+                Status::NotFound,
+                Json(ServerResponse::new_error(String::from(
+                    "Contract not found.",
+                ))),
+            ))
+        }
+        Err(_) => {
+            error!(target: "compiler", "There was DB error fetching contract {:?} {:?}", &code_id, &build_key);
+            Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::new_error(String::from(
+                    "Error getting contract.",
+                ))),
+            ))
+        }
+    }
+}
+
+// This function returns the stored contract of a request or compiles it and stores the result
+fn compile_wizard_message<T>(
+    compilation_queue: &CompilationQueue,
+    toolchains: &Toolchains,
+    feature_rules: &FeatureRules,
+    db: &MongoRepo,
+    admin: AdminAccess,
+    wizard_message: Json<WizardMessage>,
+) -> Result<Contract, ApiError<T>> {
+    // Checking input data
+    let profile = check_toolchain(toolchains, &wizard_message.toolchain)?;
+    sanity_check_wizard_message(&wizard_message, profile, feature_rules)?;
+    check_priority(&wizard_message.priority, admin.0)?;
+
+    // Hashing the contract code to create an unique identifier
+    let code_hash_str = hash_code(&wizard_message.code);
+    debug!(target: "compiler", "hash_code completed");
+
+    // The build key identifies the artifact, the same code can be built with other features or toolchains
+    let build_key = resolve_build_key(profile, &wizard_message, &code_hash_str)?;
+
+    // If contract already exists in DB, return it
+    if let Some(contract) = find_stored_contract(db, &build_key) {
+        return Ok(contract);
+    }
+
+    // If contract does not exist in DB, create it
+
+    // Sending the compilation request to the compilation thread
+    // If the same build is already running the request waits for that job
+    // The queue stores the contract once it is built, even if this request gives up on it
+    let queued_job = compilation_queue
+        .add_request(
+            wizard_message.into_inner(),
+            code_hash_str.clone(),
+            build_key,
+        )
+        .map_err(queue_error_response)?;
+
+    // Waiting for the compilation thread to finish
+    let contract = compilation_queue
+        .wait_for_client(&queued_job.job_id)
+        .and_then(|job| job.result());
+
+    // Checking if compilation was successful
+    match contract {
+        Ok(contract_unwrapped) => {
+            info!(target: "compiler", "Contract {} successfully compiled", &contract_unwrapped.code_id);
+            Ok(contract_unwrapped)
+        }
+        // If compilation failed, return the error along with the compiler diagnostics
+        Err(compilation_error) => {
+            error!(target: "compiler", "There was an error compiling the contract {}", &code_hash_str);
+
+            Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::from_error(compilation_error)),
+            )
+            .into())
+        }
+    }
+}
+
+// This function queues a check or test of the contract and waits for it, nothing is stored
+fn run_queued_job<T>(
+    compilation_queue: &CompilationQueue,
+    toolchains: &Toolchains,
+    feature_rules: &FeatureRules,
+    admin: AdminAccess,
+    wizard_message: Json<WizardMessage>,
+    kind: JobKind,
+) -> Result<CompilationJob, ApiError<T>> {
+    // Checking input data
+    let profile = check_toolchain(toolchains, &wizard_message.toolchain)?;
+    sanity_check_wizard_message(&wizard_message, profile, feature_rules)?;
+    check_priority(&wizard_message.priority, admin.0)?;
+
+    // Hashing the contract code to create an unique identifier
+    let code_hash_str = hash_code(&wizard_message.code);
+    debug!(target: "compiler", "hash_code completed");
+
+    let build_key = resolve_build_key(profile, &wizard_message, &code_hash_str)?;
+
+    let wizard_message = wizard_message.into_inner();
+    let queued_job = match kind {
+        JobKind::Build => compilation_queue.add_request(wizard_message, code_hash_str, build_key),
+        JobKind::Check => {
+            compilation_queue.add_check_request(wizard_message, code_hash_str, build_key)
+        }
+        JobKind::Test => {
+            compilation_queue.add_test_request(wizard_message, code_hash_str, build_key)
+        }
+    }
+    .map_err(queue_error_response)?;
+
+    // Waiting for the compilation thread to finish
+    compilation_queue
+        .wait_for_client(&queued_job.job_id)
+        .map_err(|error| {
+            Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::from_error(error)),
+            )
+            .into()
+        })
+}
+
+// This function turns a request the queue did not take into its response
+// Busy queues answer with the seconds the client should wait before retrying
+fn queue_error_response<T>(error: QueueError) -> ApiError<T> {
+    match error {
+        QueueError::QueueFull { retry_after } => ApiError::retry_later(
+            Status::ServiceUnavailable,
+            String::from("Compilation queue is full."),
+            retry_after,
+        ),
+        QueueError::AddressLimit { retry_after } => ApiError::retry_later(
+            Status::TooManyRequests,
+            String::from("Too many requests for this address."),
+            retry_after,
+        ),
+        QueueError::Internal(error_msg) => Custom(
+            Status::InternalServerError,
+            Json(ServerResponse::new_error(error_msg)),
+        )
+        .into(),
+    }
+}
+
+// This function creates the build key of a request from the manifest its profile renders
+fn resolve_build_key<T>(
+    profile: &ToolchainProfile,
+    wizard_message: &WizardMessage,
+    code_id: &str,
+) -> Result<String, Custom<Json<ServerResponse<T>>>> {
+    match profile.render_manifest(&wizard_message.features) {
+        Ok(manifest) => Ok(build_key(
+            code_id,
+            &manifest,
+            profile,
+            &wizard_message.build_options,
+            wizard_message.lint,
+        )),
+        Err(e) => {
+            error!(target: "compiler", "Error rendering Cargo.toml for {}: {:?}", &profile.name, e);
+            Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::new_error(String::from(
+                    "Error rendering Cargo.toml.",
+                ))),
+            ))
+        }
+    }
+}
+
 // This function creates the hash of the contract file
 pub fn hash_code(code: &String) -> String {
     let mut hasher = Sha256::new();
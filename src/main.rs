@@ -10,8 +10,10 @@ use dotenv::dotenv;
 extern crate rocket;
 
 use api::contract_api::{
-    fetch_or_compile_contract, get_contract, get_contract_deployment_by_id,
-    get_contract_deployments, get_version, store_deployment, update_deployment,
+    cancel_job, check_contract, fetch_or_compile_contract, generate_contract, get_contract,
+    get_contract_bundle, get_contract_deployment_by_id, get_contract_deployments,
+    get_contract_lock, get_job, get_queue, get_toolchains, get_version, queue_contract_compilation,
+    store_deployment, test_contract, update_deployment,
 };
 use repository::mongodb_repo::MongoRepo;
 use rocket::fairing::AdHoc;
@@ -19,8 +21,11 @@ use std::{
     sync::{atomic::AtomicBool, Arc},
     thread,
 };
-use utils::compilation_queue::CompilationQueue;
-use utils::compiler::Compiler;
+use utils::compilation_queue::{CompilationQueue, QueueLimits};
+use utils::compiler::{get_compiler_workers, Compiler};
+use utils::feature_rules::FeatureRules;
+use utils::sandbox::Sandbox;
+use utils::toolchains::Toolchains;
 
 use log::{debug, error, info};
 
@@ -41,39 +46,81 @@ fn rocket() -> _ {
     dotenv().ok();
     debug!(target: "compiler", "dotenv loaded");
 
-    // Creating compilation queue
-    let queue = CompilationQueue::new();
-    let compilation_queue = Arc::new(queue);
-    let compilation_queue_clone = compilation_queue.clone();
+    // Loading the installed toolchain profiles
+    let toolchains = Arc::new(Toolchains::init());
+    debug!(target: "compiler", "toolchains loaded");
 
-    // Setting shutdown flag to perform operations when the server is shutting down
-    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    // Loading the rules the features of every request are checked against
+    let feature_rules = Arc::new(FeatureRules::init());
+    debug!(target: "compiler", "feature rules loaded");
 
-    // Creating compiler instance and running a new thread
-    let compiler = Compiler::init(compilation_queue_clone, shutdown_flag.clone());
-    let compiler_thread = thread::spawn(move || {
-        compiler.start();
-    });
-    debug!(target: "compiler", "compiler initialized");
+    // Checking the sandbox contract builds run in
+    let sandbox = Arc::new(Sandbox::init());
+    debug!(target: "compiler", "sandbox loaded");
 
     // Initializing mongo
     let db = MongoRepo::init();
     debug!(target: "compiler", "mongo repo initialized");
 
+    // Creating compilation queue, its jobs are persisted so they survive restarts
+    // Requests over the queue limits are rejected, the workers are used to estimate when to retry
+    let compiler_workers = get_compiler_workers();
+    let queue = CompilationQueue::with_db(db.clone(), QueueLimits::init(), compiler_workers);
+    let compilation_queue = Arc::new(queue);
+
+    // Requeueing the jobs left by the previous run before the workers start
+    // The queue stores the contracts they build, like those of any other job
+    compilation_queue.recover_jobs();
+
+    // Setting shutdown flag to perform operations when the server is shutting down
+    let shutdown_flag = Arc::new(AtomicBool::new(false));
+
+    // Creating a compiler instance per worker and running each one in a new thread
+    let compiler_threads: Vec<_> = (0..compiler_workers)
+        .map(|worker_id| {
+            let compiler = Compiler::init(
+                worker_id,
+                toolchains.clone(),
+                sandbox.clone(),
+                compilation_queue.clone(),
+                shutdown_flag.clone(),
+            );
+            thread::spawn(move || {
+                compiler.start();
+            })
+        })
+        .collect();
+    debug!(target: "compiler", "{} compiler workers initialized", compiler_workers);
+
+    // The shutdown handler wakes up the idle workers so they see the shutdown flag
+    let shutdown_queue = compilation_queue.clone();
+
     // Initializing the server
     rocket::build()
         .manage(compilation_queue)
+        .manage(toolchains)
+        .manage(feature_rules)
         .manage(db)
         .manage(shutdown_flag.clone())
         .mount(
             "/",
             routes![
                 fetch_or_compile_contract,
+                queue_contract_compilation,
+                check_contract,
+                test_contract,
+                generate_contract,
+                get_job,
+                cancel_job,
+                get_queue,
                 store_deployment,
                 update_deployment,
                 get_contract_deployments,
                 get_contract_deployment_by_id,
                 get_contract,
+                get_contract_bundle,
+                get_contract_lock,
+                get_toolchains,
                 get_version
             ],
         )
@@ -81,10 +128,13 @@ fn rocket() -> _ {
             Box::pin(async move {
                 info!(target: "compiler", "Shutting down");
                 shutdown_flag.store(true, std::sync::atomic::Ordering::Relaxed);
-                let join_res = compiler_thread.join();
+                shutdown_queue.wake_workers();
+                for compiler_thread in compiler_threads {
+                    let join_res = compiler_thread.join();
 
-                if join_res.is_err() {
-                    error!(target: "compiler", "Error joining compiler thread");
+                    if join_res.is_err() {
+                        error!(target: "compiler", "Error joining compiler thread");
+                    }
                 }
 
                 info!(target: "compiler", "Shutdown complete");
@@ -96,6 +146,14 @@ fn rocket() -> _ {
 #[path = "./tests/main_post_contract_tests.rs"]
 mod main_post_contract_test;
 
+#[cfg(test)]
+#[path = "./tests/main_post_contract_check_tests.rs"]
+mod main_post_contract_check_test;
+
+#[cfg(test)]
+#[path = "./tests/main_post_contract_test_tests.rs"]
+mod main_post_contract_test_test;
+
 #[cfg(test)]
 #[path = "./tests/main_get_contract_tests.rs"]
 mod main_get_contract_test;
@@ -111,3 +169,15 @@ mod main_get_deployments_test;
 #[cfg(test)]
 #[path = "./tests/main_get_version_test.rs"]
 mod main_get_version_test;
+
+#[cfg(test)]
+#[path = "./tests/main_get_toolchains_test.rs"]
+mod main_get_toolchains_test;
+
+#[cfg(test)]
+#[path = "./tests/main_get_jobs_tests.rs"]
+mod main_get_jobs_test;
+
+#[cfg(test)]
+#[path = "./tests/main_post_generate_tests.rs"]
+mod main_post_generate_test;
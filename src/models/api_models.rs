@@ -1,5 +1,10 @@
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
+use super::db_models::{Contract, JobPriority};
+
 // Generic server response
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct ServerResponse<T> {
@@ -17,21 +22,245 @@ impl<T> ServerResponse<T> {
     pub fn new_error(message: String) -> Self {
         ServerResponse {
             data: None,
-            error: Some(ServerError { message }),
+            error: Some(ServerError::new(message)),
+        }
+    }
+
+    pub fn from_error(error: ServerError) -> Self {
+        ServerResponse {
+            data: None,
+            error: Some(error),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+// Error response of the endpoints that queue jobs
+// Requests rejected because the queue is busy tell the client when to retry with a Retry-After header
+#[derive(Responder)]
+pub enum ApiError<T> {
+    Failed(Custom<Json<ServerResponse<T>>>),
+    RetryLater(Custom<Json<ServerResponse<T>>>, Header<'static>),
+}
+
+impl<T> ApiError<T> {
+    pub fn retry_later(status: Status, message: String, retry_after: u64) -> Self {
+        ApiError::RetryLater(
+            Custom(status, Json(ServerResponse::new_error(message))),
+            Header::new("Retry-After", retry_after.to_string()),
+        )
+    }
+}
+
+impl<T> From<Custom<Json<ServerResponse<T>>>> for ApiError<T> {
+    fn from(error: Custom<Json<ServerResponse<T>>>) -> Self {
+        ApiError::Failed(error)
+    }
+}
+
+// A build artifact sent as a file to download
+#[derive(Responder)]
+pub struct FileDownload {
+    inner: (ContentType, String),
+    content_disposition: Header<'static>,
+}
+
+impl FileDownload {
+    pub fn new(file_name: &str, content_type: ContentType, content: String) -> Self {
+        FileDownload {
+            inner: (content_type, content),
+            content_disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ServerError {
     pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Vec<Diagnostic>>,
+    // Reasons of every feature rule a request violates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violations: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ServerError {
+    pub fn new(message: String) -> Self {
+        ServerError {
+            message,
+            diagnostics: None,
+            violations: None,
+        }
+    }
+
+    pub fn with_diagnostics(message: String, diagnostics: Vec<Diagnostic>) -> Self {
+        ServerError {
+            message,
+            diagnostics: Some(diagnostics),
+            violations: None,
+        }
+    }
+
+    pub fn with_violations(violations: Vec<String>) -> Self {
+        ServerError {
+            message: violations.join("; "),
+            diagnostics: None,
+            violations: Some(violations),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+// A compiler error or warning pointing to the submitted lib.rs
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub code: Option<String>,
+    pub span: Option<DiagnosticSpan>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiagnosticSpan {
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+// Result of one of the #[ink::test] functions of the contract, with the output it captured
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub output: String,
+}
+
+// Per-test report of running the unit tests of a contract
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TestReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub tests: Vec<TestCase>,
+}
+
+impl TestReport {
+    pub fn new(tests: Vec<TestCase>) -> Self {
+        let count = |outcome: TestOutcome| tests.iter().filter(|t| t.outcome == outcome).count();
+
+        TestReport {
+            passed: count(TestOutcome::Passed),
+            failed: count(TestOutcome::Failed),
+            ignored: count(TestOutcome::Ignored),
+            tests,
+        }
+    }
+}
+
+// State of the compilation queue, averages are missing until the first job goes through
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QueueSummary {
+    pub depth: usize,
+    pub active_builds: usize,
+    pub workers: usize,
+    pub average_build_ms: Option<u64>,
+    pub average_wait_ms: Option<u64>,
+}
+
+// Cargo profile a contract is built with, debug builds keep the ink::env::debug_println! output
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildMode {
+    Debug,
+    #[default]
+    Release,
+}
+
+// Options passed to cargo contract build, every field is optional in the request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct BuildOptions {
+    pub mode: BuildMode,
+    // wasm-opt optimization level, one of 0, 1, 2, 3, 4, s or z
+    pub optimization_passes: Option<String>,
+    pub keep_debug_symbols: bool,
+    // Features of the contract crate itself, not openbrush features
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WizardMessage {
     pub address: String,
     pub code: String,
     pub features: Vec<String>,
+    #[serde(default)]
+    pub toolchain: Option<String>,
+    #[serde(default)]
+    pub build_options: BuildOptions,
+    // Lints the contract with clippy and the ink! linter after building it
+    #[serde(default)]
+    pub lint: bool,
+    // Priority class of the job, normal when missing
+    #[serde(default)]
+    pub priority: Option<JobPriority>,
+}
+
+// Parameters of a contract generated by the service instead of the frontend
+// Name, symbol, decimals and initial supply are only used by the standards that have them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerateMessage {
+    pub address: String,
+    // One of the contract standards, psp22, psp34 or psp37
+    pub standard: String,
+    // Extensions added to the standard, like pausable, ownable or access-control
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub name: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    // Amount minted to the owner on deployment, a string so big amounts survive JSON parsing
+    #[serde(default)]
+    pub initial_supply: Option<String>,
+    // Owner of ownable contracts and admin of access-control ones, the deployer when missing
+    #[serde(default)]
+    pub owner: Option<String>,
+    // Compiles the generated contract like POST /contract does
+    #[serde(default)]
+    pub compile: bool,
+    #[serde(default)]
+    pub toolchain: Option<String>,
+    #[serde(default)]
+    pub build_options: BuildOptions,
+    #[serde(default)]
+    pub priority: Option<JobPriority>,
+}
+
+// Source of a generated contract, along with the contract compiled from it when requested
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GeneratedContract {
+    pub code: String,
+    pub features: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<Contract>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
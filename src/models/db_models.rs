@@ -1,7 +1,8 @@
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 
-use super::api_models::DeployMessage;
+use super::api_models::{DeployMessage, Diagnostic, ServerError, TestReport, WizardMessage};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Contract {
@@ -9,8 +10,20 @@ pub struct Contract {
     #[serde(skip_serializing)]
     pub id: Option<ObjectId>,
     pub code_id: String,
+    // Contracts stored before the build key existed have an empty one and are never reused
+    #[serde(default)]
+    pub build_key: String,
     pub metadata: String,
     pub wasm: Vec<u8>,
+    // The .contract bundle written by cargo contract, with the metadata and the wasm together
+    #[serde(default)]
+    pub bundle: String,
+    // Cargo.lock pinning the dependency versions the contract was built with
+    #[serde(default)]
+    pub cargo_lock: String,
+    // Findings of clippy and the ink! linter, only for contracts built with lint enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lints: Option<Vec<Diagnostic>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -46,3 +59,178 @@ impl Deployment {
         }
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Compiling,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+// Builds produce the contract artifacts, checks only type-check the code and report diagnostics
+// and tests run the unit tests of the contract
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Build,
+    Check,
+    Test,
+}
+
+// Priority class of a job, workers always take the jobs of the highest class first
+// Checks are high priority because they are cheap, high priority builds are reserved for admins
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CompilationJob {
+    pub id: String,
+    pub code_id: String,
+    pub build_key: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub contract: Option<Contract>,
+    pub error: Option<ServerError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Vec<Diagnostic>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_report: Option<TestReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<BuildMetrics>,
+    // Position in the queue and seconds until a worker takes the job, only while it is queued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_start_secs: Option<u64>,
+    #[serde(skip)]
+    pub finished_at: Option<Instant>,
+    // Address of the request that created the job, used for the per-address limit
+    #[serde(skip)]
+    pub address: String,
+    // Requests blocked until the job finishes, the job is cancelled when the last one gives up
+    #[serde(skip)]
+    pub waiters: usize,
+    // Release tokens of the async requests polling the job, each one keeps it alive until it is released
+    #[serde(skip)]
+    pub pollers: Vec<String>,
+    // Token the async request that got this snapshot releases the job with, never kept in the queue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_token: Option<String>,
+}
+
+// Job persisted in the Jobs collection while it is queued or compiling, so it survives restarts
+// The record is deleted once the job finishes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobRecord {
+    pub job_id: String,
+    pub code_id: String,
+    pub build_key: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub wizard_message: WizardMessage,
+    // Number of times a worker took the job, a job left compiling by a crash is retried while it is low
+    pub attempts: u32,
+}
+
+// Timings of a build, the cache is warm when the worker already built the same feature set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BuildMetrics {
+    pub warm_cache: bool,
+    pub duration_ms: u64,
+}
+
+impl CompilationJob {
+    pub fn new(code_id: &str, build_key: &str, kind: JobKind) -> Self {
+        CompilationJob {
+            id: ObjectId::new().to_hex(),
+            code_id: code_id.to_owned(),
+            build_key: build_key.to_owned(),
+            kind,
+            status: JobStatus::Queued,
+            contract: None,
+            error: None,
+            diagnostics: None,
+            test_report: None,
+            metrics: None,
+            queue_position: None,
+            estimated_start_secs: None,
+            finished_at: None,
+            address: String::new(),
+            waiters: 0,
+            pollers: vec![],
+            release_token: None,
+        }
+    }
+
+    pub fn finish(&mut self, result: Result<Contract, ServerError>) {
+        match result {
+            Ok(contract) => {
+                self.status = JobStatus::Succeeded;
+                self.contract = Some(contract);
+            }
+            Err(error) => {
+                self.status = JobStatus::Failed;
+                self.error = Some(error);
+            }
+        }
+        self.finished_at = Some(Instant::now());
+    }
+
+    pub fn finish_check(&mut self, result: Result<Vec<Diagnostic>, ServerError>) {
+        match result {
+            Ok(diagnostics) => {
+                self.status = JobStatus::Succeeded;
+                self.diagnostics = Some(diagnostics);
+            }
+            Err(error) => {
+                self.status = JobStatus::Failed;
+                self.error = Some(error);
+            }
+        }
+        self.finished_at = Some(Instant::now());
+    }
+
+    pub fn finish_test(&mut self, result: Result<TestReport, ServerError>) {
+        match result {
+            Ok(test_report) => {
+                self.status = JobStatus::Succeeded;
+                self.test_report = Some(test_report);
+            }
+            Err(error) => {
+                self.status = JobStatus::Failed;
+                self.error = Some(error);
+            }
+        }
+        self.finished_at = Some(Instant::now());
+    }
+
+    pub fn cancel(&mut self) {
+        self.status = JobStatus::Cancelled;
+        self.error = Some(ServerError::new(String::from("Job cancelled.")));
+        self.finished_at = Some(Instant::now());
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+
+    pub fn result(&self) -> Result<Contract, ServerError> {
+        match (&self.contract, &self.error) {
+            (Some(contract), _) => Ok(contract.clone()),
+            (None, Some(error)) => Err(error.clone()),
+            (None, None) => Err(ServerError::new(String::from("Error compiling contract"))),
+        }
+    }
+}
@@ -1,19 +1,23 @@
 use std::env;
 
 use crate::models::api_models::{GetDeploymentsMessage, UpdateDeployMessage};
-use crate::models::db_models::{Contract, Deployment};
+use crate::models::db_models::{Contract, Deployment, JobRecord, JobStatus};
 use crate::utils::common::string_to_object_id;
-use mongodb::results::UpdateResult;
+use mongodb::options::FindOptions;
+use mongodb::results::{DeleteResult, UpdateResult};
 use mongodb::{
-    bson::doc,
+    bson::{doc, to_bson},
     results::InsertOneResult,
     sync::{Client, Collection},
 };
 
 use log::{debug, error};
+
+#[derive(Clone)]
 pub struct MongoRepo {
     pub contracts: Collection<Contract>,
     pub deployments: Collection<Deployment>,
+    pub jobs: Collection<JobRecord>,
 }
 
 // Mongo Repo implementation
@@ -40,6 +44,7 @@ impl MongoRepo {
         let db = client.database("ContractWizard");
         let contracts: Collection<Contract> = db.collection("Contracts");
         let deployments: Collection<Deployment> = db.collection("Deployments");
+        let jobs: Collection<JobRecord> = db.collection("Jobs");
 
         // Test db is up and running
         let ping_database = client
@@ -58,6 +63,7 @@ impl MongoRepo {
         MongoRepo {
             contracts,
             deployments,
+            jobs,
         }
     }
 
@@ -80,6 +86,56 @@ impl MongoRepo {
         Ok(contract)
     }
 
+    // Get the contract built from the same source, manifest and toolchain
+    pub fn get_contract_by_build_key(
+        &self,
+        build_key: &str,
+    ) -> Result<Option<Contract>, Box<dyn std::error::Error>> {
+        let filter = doc! {"build_key": build_key};
+        let contract = self.contracts.find_one(filter, None)?;
+        Ok(contract)
+    }
+
+    // Persist a queued job
+    pub fn create_job(
+        &self,
+        new_job: &JobRecord,
+    ) -> Result<InsertOneResult, Box<dyn std::error::Error>> {
+        let job = self.jobs.insert_one(new_job, None)?;
+        Ok(job)
+    }
+
+    // Mark a persisted job as taken by a worker, counting the attempt
+    pub fn mark_job_compiling(
+        &self,
+        job_id: &str,
+    ) -> Result<UpdateResult, Box<dyn std::error::Error>> {
+        let filter = doc! {"job_id": job_id};
+        let update = doc! {
+            "$set": {"status": to_bson(&JobStatus::Compiling)?},
+            "$inc": {"attempts": 1},
+        };
+        let job = self.jobs.update_one(filter, update, None)?;
+        Ok(job)
+    }
+
+    // Delete a persisted job once it is finished
+    pub fn delete_job(&self, job_id: &str) -> Result<DeleteResult, Box<dyn std::error::Error>> {
+        let filter = doc! {"job_id": job_id};
+        let job = self.jobs.delete_one(filter, None)?;
+        Ok(job)
+    }
+
+    // Fetch the persisted jobs in the order they were queued
+    pub fn get_jobs(&self) -> Result<Vec<JobRecord>, Box<dyn std::error::Error>> {
+        let options = FindOptions::builder().sort(doc! {"_id": 1}).build();
+        let jobs = self.jobs.find(None, options)?;
+
+        let jobs_vec: Vec<JobRecord> = jobs.filter_map(|job| job.ok()).collect();
+
+        Ok(jobs_vec)
+    }
+
     // Create a deployment in the database
     pub fn create_deployment(
         &self,
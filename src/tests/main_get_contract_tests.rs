@@ -79,4 +79,29 @@ mod get_contract_test {
         assert_eq!(db_res.deleted_count, 1);
         client.terminate();
     }
+
+    #[test]
+    fn get_contract_bundle_requires_code_id_or_build_key() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.get(uri!("/contract/bundle")).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("code_id or build_key is required."));
+        client.terminate();
+    }
+
+    // This tests is assuming build key 1 is not used
+    #[test]
+    fn get_contract_lock_not_existing_build_key_returns_not_found_error() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.get(uri!("/contract/lock?build_key=1")).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Contract not found."));
+        client.terminate();
+    }
 }
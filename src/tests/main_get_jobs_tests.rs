@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod get_jobs_test {
+    use super::super::*;
+    use crate::models::api_models::{QueueSummary, ServerResponse};
+    use crate::models::db_models::{CompilationJob, JobStatus};
+    use crate::MongoRepo;
+    use mongodb::bson::doc;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    const VALID_INK_SC: &str = r#"#![cfg_attr(not(feature = \"std\"), no_std)] #![feature(min_specialization)] #[openbrush::contract] pub mod my_psp23 { use openbrush::contracts::psp22::*; use openbrush::traits::Storage; #[ink(storage)] #[derive(Default, Storage)] pub struct Contract { #[storage_field] psp22: psp22::Data, } impl PSP22 for Contract {} impl Contract { #[ink(constructor)] pub fn new(initial_supply: Balance) -> Self { let mut _instance = Self::default(); _instance._mint_to(_instance.env().caller(), initial_supply); _instance } } }"#;
+
+    #[test]
+    fn get_job_not_existing_id_returns_not_found_error() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.get(uri!("/jobs/1")).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("No job found for ID 1"));
+        client.terminate();
+    }
+
+    #[test]
+    fn cancel_job_without_release_token_is_forbidden() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.delete(uri!("/jobs/1")).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Invalid release token."));
+        client.terminate();
+    }
+
+    #[test]
+    fn get_queue_returns_summary() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.get(uri!("/queue")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let json: ServerResponse<QueueSummary> = response.into_json().unwrap();
+        let summary = json.data.unwrap();
+        assert!(summary.workers >= 1);
+        assert!(summary.active_builds <= summary.workers);
+        client.terminate();
+    }
+
+    #[test]
+    fn post_contract_async_validates_input() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .post(uri!("/contract?async=true"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "something", "features": ["asdf"] }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Feature not allowed"));
+        client.terminate();
+    }
+
+    #[test]
+    fn post_contract_async_returns_a_job_to_poll() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let db = client.rocket().state::<MongoRepo>().unwrap();
+        let body = format!(
+            r#"{{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "{}", "features": ["psp22"] }}"#,
+            VALID_INK_SC
+        );
+        let response = client
+            .post(uri!("/contract?async=true"))
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let json: ServerResponse<CompilationJob> = response.into_json().unwrap();
+        let mut job = json.data.unwrap();
+        let release_token = job.release_token.clone().unwrap();
+
+        // Polling the job until the compiler is done with it
+        while job.status == JobStatus::Queued || job.status == JobStatus::Compiling {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            let response = client.get(format!("/jobs/{}", job.id)).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let json: ServerResponse<CompilationJob> = response.into_json().unwrap();
+            job = json.data.unwrap();
+        }
+
+        // Only the client that queued the job gets its release token
+        assert_eq!(job.release_token, None);
+        assert_eq!(job.status, JobStatus::Succeeded);
+        let contract = job.contract.unwrap();
+
+        let status = client
+            .delete(format!("/jobs/{}?release_token={}", job.id, release_token))
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::Conflict);
+
+        // The contract is stored before the job is finished
+        let db_res = db
+            .contracts
+            .delete_many(doc! {"code_id": contract.code_id}, None)
+            .unwrap();
+        assert_eq!(db_res.deleted_count, 1);
+        client.terminate();
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod get_toolchains_test {
+    use super::super::*;
+    use crate::models::api_models::ServerResponse;
+    use crate::utils::toolchains::ToolchainProfile;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    #[test]
+    fn get_toolchains_lists_installed_profiles() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client.get(uri!("/toolchains")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let json: ServerResponse<Vec<ToolchainProfile>> = response.into_json().unwrap();
+        let profiles = json.data.unwrap();
+        assert!(!profiles.is_empty());
+        assert!(profiles
+            .iter()
+            .all(|profile| profile.cargo_contract_version.is_some()));
+        client.terminate();
+    }
+
+    #[test]
+    fn post_contract_with_unknown_toolchain_error() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .post(uri!("/contract"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "something", "features": ["psp22"], "toolchain": "ink1" }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Toolchain not available: ink1"));
+        client.terminate();
+    }
+}
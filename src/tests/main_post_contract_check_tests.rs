@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod post_contract_check_test {
+    use super::super::*;
+    use crate::api::contract_api::hash_code;
+    use crate::models::api_models::{Diagnostic, DiagnosticSeverity, ServerResponse};
+    use crate::MongoRepo;
+    use mongodb::bson::doc;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    const VALID_INK_SC: &str = r#"#![cfg_attr(not(feature = \"std\"), no_std)] #![feature(min_specialization)] #[openbrush::contract] pub mod my_psp24 { use openbrush::contracts::psp22::*; use openbrush::traits::Storage; #[ink(storage)] #[derive(Default, Storage)] pub struct Contract { #[storage_field] psp22: psp22::Data, } impl PSP22 for Contract {} impl Contract { #[ink(constructor)] pub fn new(initial_supply: Balance) -> Self { let mut _instance = Self::default(); _instance._mint_to(_instance.env().caller(), initial_supply); _instance } } }"#;
+
+    const INVALID_INK_SC: &str = r#"#![cfg_attr(not(feature = \"std\"), no_std)] #![feature(min_specialization)] #[openbrush::contract] pub mod my_psp24 { use openbrush::contracts::psp22::*; use openbrush::traits::Storage; #[ink(storage)] #[derive(Default, Storage)] pub struct Contract { #[storage_field] psp22: psp22::Data, } impl PSP22 for Contract {} impl Contract { #[ink(constructor)] pub fn new(initial_supply: Balance) -> Self { let mut _instance = Self::default(); _instance._mint_to(_instance.env().caller(), supply); _instance } } }"#;
+
+    #[test]
+    fn post_contract_check_validates_input() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .post(uri!("/contract/check"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "something", "features": ["asdf"] }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Feature not allowed"));
+        client.terminate();
+    }
+
+    #[test]
+    fn post_contract_check_valid_code_does_not_store_contract() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let db = client.rocket().state::<MongoRepo>().unwrap();
+
+        let body = format!(
+            r#"{{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "{}", "features": ["psp22"] }}"#,
+            VALID_INK_SC
+        );
+        let response = client.post(uri!("/contract/check")).body(body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let json: ServerResponse<Vec<Diagnostic>> = response.into_json().unwrap();
+        assert!(json
+            .data
+            .unwrap()
+            .iter()
+            .all(|diagnostic| diagnostic.severity == DiagnosticSeverity::Warning));
+
+        let code_id = hash_code(&VALID_INK_SC.replace("\\\"", "\""));
+        let stored = db
+            .contracts
+            .find_one(doc! {"code_id": code_id}, None)
+            .unwrap();
+        assert!(stored.is_none());
+        client.terminate();
+    }
+
+    #[test]
+    fn post_contract_check_returns_diagnostics() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+
+        let body = format!(
+            r#"{{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "{}", "features": ["psp22"] }}"#,
+            INVALID_INK_SC
+        );
+        let response = client.post(uri!("/contract/check")).body(body).dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        let json: ServerResponse<Vec<Diagnostic>> = response.into_json().unwrap();
+        let error = json.error.unwrap();
+        assert_eq!(error.message, "Contract check failed.");
+        assert!(error
+            .diagnostics
+            .unwrap()
+            .iter()
+            .any(|diagnostic| diagnostic.code.as_deref() == Some("E0425")));
+        client.terminate();
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod post_contract_test_test {
+    use super::super::*;
+    use crate::models::api_models::{ServerResponse, TestOutcome, TestReport};
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    const INK_SC_WITH_TESTS: &str = r#"#![cfg_attr(not(feature = \"std\"), no_std)] #![feature(min_specialization)] #[openbrush::contract] pub mod my_psp25 { use openbrush::contracts::psp22::*; use openbrush::traits::Storage; #[ink(storage)] #[derive(Default, Storage)] pub struct Contract { #[storage_field] psp22: psp22::Data, } impl PSP22 for Contract {} impl Contract { #[ink(constructor)] pub fn new(initial_supply: Balance) -> Self { let mut _instance = Self::default(); _instance._mint_to(_instance.env().caller(), initial_supply); _instance } } #[cfg(test)] mod tests { use super::*; #[ink::test] fn total_supply_works() { let contract = Contract::new(100); assert_eq!(PSP22::total_supply(&contract), 100); } #[ink::test] fn total_supply_fails() { let contract = Contract::new(100); assert_eq!(PSP22::total_supply(&contract), 200); } } }"#;
+
+    #[test]
+    fn post_contract_test_validates_input() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .post(uri!("/contract/test"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "something", "features": ["asdf"] }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Feature not allowed"));
+        client.terminate();
+    }
+
+    #[test]
+    fn post_contract_test_reports_every_test() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+
+        let body = format!(
+            r#"{{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "code": "{}", "features": ["psp22"] }}"#,
+            INK_SC_WITH_TESTS
+        );
+        let response = client.post(uri!("/contract/test")).body(body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let json: ServerResponse<TestReport> = response.into_json().unwrap();
+        let report = json.data.unwrap();
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failed, 1);
+
+        let failed = report
+            .tests
+            .iter()
+            .find(|test| test.outcome == TestOutcome::Failed)
+            .unwrap();
+        assert_eq!(failed.name, "my_psp25::tests::total_supply_fails");
+        assert!(failed.output.contains("panicked"));
+        client.terminate();
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod post_generate_test {
+    use super::super::*;
+    use crate::models::api_models::{GeneratedContract, ServerResponse};
+    use crate::MongoRepo;
+    use mongodb::bson::doc;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+
+    #[test]
+    fn post_generate_validates_input() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .post(uri!("/generate"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "standard": "psp22", "name": "My \"Token\"" }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Contract name not valid."));
+        client.terminate();
+    }
+
+    #[test]
+    fn post_generate_returns_the_rendered_contract() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let response = client
+            .post(uri!("/generate"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "standard": "psp22", "extensions": ["ownable"], "name": "My Token", "symbol": "MTK", "initial_supply": "1000" }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let json: ServerResponse<GeneratedContract> = response.into_json().unwrap();
+        let generated = json.data.unwrap();
+        assert_eq!(generated.features, vec!["psp22", "ownable"]);
+        assert!(generated.code.contains("impl Ownable for Contract {}"));
+        assert!(generated.contract.is_none());
+        client.terminate();
+    }
+
+    #[test]
+    fn post_generate_compiles_the_rendered_contract() {
+        let client = Client::tracked(rocket()).expect("valid rocket instance");
+        let db = client.rocket().state::<MongoRepo>().unwrap();
+        let response = client
+            .post(uri!("/generate"))
+            .body(r#"{ "address": "XYtLu1tuJ8zBc3NZGSDnU5kSig7j6mHY1FBg8YXNkk4NMmM", "standard": "psp22", "extensions": ["pausable", "ownable"], "name": "My Token", "symbol": "MTK", "initial_supply": "1000", "compile": true }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let json: ServerResponse<GeneratedContract> = response.into_json().unwrap();
+        let contract = json.data.unwrap().contract.unwrap();
+        assert!(!contract.wasm.is_empty());
+
+        // Removing the contract so the next run compiles it again instead of hitting the cache
+        let db_res = db
+            .contracts
+            .delete_many(doc! {"code_id": contract.code_id}, None)
+            .unwrap();
+        assert_eq!(db_res.deleted_count, 1);
+        client.terminate();
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod build_key_test {
+    use super::super::*;
+
+    fn profile() -> ToolchainProfile {
+        ToolchainProfile {
+            name: String::from("ink4-openbrush3"),
+            rust_toolchain: String::from("nightly-2023-02-07"),
+            cargo_contract_version: Some(String::from("cargo-contract-contract 2.2.1")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_build_key_is_stable() {
+        assert_eq!(
+            build_key(
+                "code_id",
+                "manifest",
+                &profile(),
+                &BuildOptions::default(),
+                false
+            ),
+            build_key(
+                "code_id",
+                "manifest",
+                &profile(),
+                &BuildOptions::default(),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn test_build_key_changes_with_every_input() {
+        let key = build_key(
+            "code_id",
+            "manifest",
+            &profile(),
+            &BuildOptions::default(),
+            false,
+        );
+
+        let mut other_toolchain = profile();
+        other_toolchain.rust_toolchain = String::from("1.77.0");
+        let mut other_cargo_contract = profile();
+        other_cargo_contract.cargo_contract_version = Some(String::from("cargo-contract 4.0.0"));
+        let mut other_profile = profile();
+        other_profile.name = String::from("ink5");
+
+        assert_ne!(
+            key,
+            build_key(
+                "other_code_id",
+                "manifest",
+                &profile(),
+                &BuildOptions::default(),
+                false
+            )
+        );
+        assert_ne!(
+            key,
+            build_key(
+                "code_id",
+                "other_manifest",
+                &profile(),
+                &BuildOptions::default(),
+                false
+            )
+        );
+        assert_ne!(
+            key,
+            build_key(
+                "code_id",
+                "manifest",
+                &other_toolchain,
+                &BuildOptions::default(),
+                false
+            )
+        );
+        assert_ne!(
+            key,
+            build_key(
+                "code_id",
+                "manifest",
+                &other_cargo_contract,
+                &BuildOptions::default(),
+                false
+            )
+        );
+        assert_ne!(
+            key,
+            build_key(
+                "code_id",
+                "manifest",
+                &other_profile,
+                &BuildOptions::default(),
+                false
+            )
+        );
+        assert_ne!(
+            build_key(
+                "code_idmani",
+                "fest",
+                &profile(),
+                &BuildOptions::default(),
+                false
+            ),
+            build_key(
+                "code_id",
+                "manifest",
+                &profile(),
+                &BuildOptions::default(),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn test_build_key_changes_with_build_options() {
+        let default_options = BuildOptions::default();
+        let key = build_key("code_id", "manifest", &profile(), &default_options, false);
+
+        let debug = BuildOptions {
+            mode: BuildMode::Debug,
+            ..Default::default()
+        };
+        let optimized = BuildOptions {
+            optimization_passes: Some(String::from("3")),
+            ..Default::default()
+        };
+        let debug_symbols = BuildOptions {
+            keep_debug_symbols: true,
+            ..Default::default()
+        };
+        let features = BuildOptions {
+            features: vec![String::from("a"), String::from("b")],
+            ..Default::default()
+        };
+
+        assert_ne!(
+            key,
+            build_key("code_id", "manifest", &profile(), &debug, false)
+        );
+        assert_ne!(
+            key,
+            build_key("code_id", "manifest", &profile(), &optimized, false)
+        );
+        assert_ne!(
+            key,
+            build_key("code_id", "manifest", &profile(), &debug_symbols, false)
+        );
+        assert_ne!(
+            key,
+            build_key("code_id", "manifest", &profile(), &features, false)
+        );
+
+        // The order the features are requested in does not matter
+        let reversed = BuildOptions {
+            features: vec![String::from("b"), String::from("a")],
+            ..Default::default()
+        };
+        assert_eq!(
+            build_key("code_id", "manifest", &profile(), &features, false),
+            build_key("code_id", "manifest", &profile(), &reversed, false)
+        );
+    }
+
+    #[test]
+    fn test_build_key_changes_with_lint() {
+        let options = BuildOptions::default();
+        assert_ne!(
+            build_key("code_id", "manifest", &profile(), &options, false),
+            build_key("code_id", "manifest", &profile(), &options, true)
+        );
+    }
+}
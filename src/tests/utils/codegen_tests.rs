@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod codegen_test {
+    use super::super::*;
+
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    fn generate_message(standard: &str, extensions: &[&str]) -> GenerateMessage {
+        GenerateMessage {
+            address: String::from(BOB),
+            standard: standard.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            name: String::from("My Token"),
+            symbol: Some(String::from("MTK")),
+            decimals: None,
+            initial_supply: Some(String::from("1000")),
+            owner: None,
+            compile: false,
+            toolchain: None,
+            build_options: Default::default(),
+            priority: None,
+        }
+    }
+
+    #[test]
+    fn test_render_psp22_with_ownable() {
+        let code = render_contract(&generate_message("psp22", &["ownable"]));
+
+        assert!(code.contains("pub mod my_psp22 {"));
+        assert!(code.contains("use openbrush::contracts::psp22::extensions::metadata::*;"));
+        assert!(code.contains("use openbrush::contracts::ownable::*;"));
+        assert!(code.contains("        #[storage_field]\n        ownable: ownable::Data,"));
+        assert!(code.contains("    impl Ownable for Contract {}"));
+        assert!(code.contains("_instance.metadata.name = Some(String::from(\"My Token\"));"));
+        assert!(code.contains("_instance.metadata.symbol = Some(String::from(\"MTK\"));"));
+        assert!(code.contains("_instance.metadata.decimals = 18;"));
+        assert!(code.contains("._mint_to(_instance.env().caller(), 1000)"));
+        assert!(code.contains("_instance._init_with_owner(_instance.env().caller());"));
+        assert!(!code.contains("{{"));
+        assert!(!code.contains("change_state"));
+    }
+
+    #[test]
+    fn test_render_pausable_guards_transfers_and_pausing() {
+        let code = render_contract(&generate_message("psp34", &["pausable", "access-control"]));
+
+        assert!(code.contains("impl psp34::Transfer for Contract {"));
+        assert!(code.contains("_id: &Id,"));
+        assert!(code.contains(") -> Result<(), PSP34Error> {"));
+        assert!(code.contains("#[openbrush::modifiers(only_role(DEFAULT_ADMIN_ROLE))]"));
+        assert!(code.contains("pub fn change_state(&mut self) -> Result<(), PSP34Error> {"));
+        assert!(code.contains("let collection_id = _instance.collection_id();"));
+        assert!(!code.contains("_mint_to"));
+    }
+
+    #[test]
+    fn test_render_owner_address() {
+        let mut message = generate_message("psp37", &["ownable"]);
+        message.owner = Some(String::from(BOB));
+
+        let code = render_contract(&message);
+        assert!(code.contains("_instance._init_with_owner(AccountId::from([0x8e, 0xaf,"));
+        assert!(!code.contains("metadata"));
+    }
+
+    #[test]
+    fn test_generated_features_start_with_the_standard() {
+        let message = generate_message("psp22", &["pausable", "ownable", "pausable"]);
+        assert_eq!(
+            generated_features(&message),
+            vec!["psp22", "pausable", "ownable"]
+        );
+    }
+
+    #[test]
+    fn test_check_generate_message() {
+        assert!(check_generate_message(&generate_message("psp22", &["ownable"])).is_ok());
+        assert_eq!(
+            check_generate_message(&generate_message("psp99", &[])),
+            Err("Contract standard not allowed.".to_string())
+        );
+        assert_eq!(
+            check_generate_message(&generate_message("psp22", &["psp34"])),
+            Err("Extension not allowed.".to_string())
+        );
+
+        let mut message = generate_message("psp22", &[]);
+        message.name = String::from("\"); panic!(\"");
+        assert_eq!(
+            check_generate_message(&message),
+            Err("Contract name not valid.".to_string())
+        );
+
+        let mut message = generate_message("psp22", &[]);
+        message.symbol = Some(String::from("M T K"));
+        assert_eq!(
+            check_generate_message(&message),
+            Err("Contract symbol not valid.".to_string())
+        );
+
+        let mut message = generate_message("psp22", &[]);
+        message.initial_supply = Some(String::from("-1"));
+        assert_eq!(
+            check_generate_message(&message),
+            Err("Initial supply not valid.".to_string())
+        );
+
+        let mut message = generate_message("psp22", &[]);
+        message.owner = Some(String::from("not an address"));
+        assert_eq!(
+            check_generate_message(&message),
+            Err("Owner is not valid.".to_string())
+        );
+    }
+}
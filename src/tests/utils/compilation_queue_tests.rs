@@ -0,0 +1,820 @@
+#[cfg(test)]
+mod compilation_queue_test {
+    use super::super::*;
+    use std::thread;
+
+    const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+    fn wizard_message() -> WizardMessage {
+        WizardMessage {
+            address: String::from(BOB),
+            code: String::from("code"),
+            features: vec![String::from("psp22")],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
+        }
+    }
+
+    fn contract(code_id: &str) -> Contract {
+        Contract {
+            id: None,
+            code_id: code_id.to_string(),
+            build_key: String::from("build_key"),
+            metadata: String::from("{}"),
+            wasm: vec![0, 97, 115, 109],
+            bundle: String::from("{}"),
+            cargo_lock: String::from("version = 3"),
+            lints: None,
+        }
+    }
+
+    #[test]
+    fn test_add_request_creates_queued_job() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        let job = queue.get_job(&job_id).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.code_id, "code_id");
+    }
+
+    #[test]
+    fn test_take_request_marks_job_as_compiling() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        let request = queue.take_request().unwrap();
+        assert_eq!(request.job_id, job_id);
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Compiling);
+        assert!(queue.take_request().is_none());
+    }
+
+    #[test]
+    fn test_wait_for_client_returns_result_once_finished() {
+        let queue = Arc::new(CompilationQueue::new());
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        let compiler_queue = queue.clone();
+        let compiler = thread::spawn(move || {
+            let request = compiler_queue.take_request().unwrap();
+            compiler_queue.finish_job(&request.job_id, Ok(contract(&request.code_id)));
+        });
+
+        let result = queue.wait_for_client(&job_id).and_then(|job| job.result());
+        compiler.join().unwrap();
+
+        assert_eq!(result, Ok(contract("code_id")));
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Succeeded);
+    }
+
+    #[test]
+    fn test_failed_job_keeps_error() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        queue.finish_job(
+            &job_id,
+            Err(ServerError::new(String::from("Error compiling contract."))),
+        );
+
+        let job = queue.get_job(&job_id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(
+            job.error,
+            Some(ServerError::new(String::from("Error compiling contract.")))
+        );
+        assert_eq!(
+            queue.wait_for_client(&job_id).and_then(|job| job.result()),
+            Err(ServerError::new(String::from("Error compiling contract.")))
+        );
+    }
+
+    #[test]
+    fn test_add_finished_job() {
+        let queue = CompilationQueue::new();
+        let job_id = queue.add_finished_job(contract("code_id")).unwrap();
+
+        let job = queue.get_job(&job_id).unwrap();
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.contract, Some(contract("code_id")));
+        assert!(queue.take_request().is_none());
+    }
+
+    #[test]
+    fn test_fail_queued_requests() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        queue.fail_queued_requests("Compiler shutting down.");
+
+        assert!(queue.take_request().is_none());
+        assert_eq!(
+            queue.wait_for_client(&job_id).and_then(|job| job.result()),
+            Err(ServerError::new(String::from("Compiler shutting down.")))
+        );
+    }
+
+    fn job_record(job_id: &str, kind: JobKind, status: JobStatus, attempts: u32) -> JobRecord {
+        JobRecord {
+            job_id: job_id.to_string(),
+            code_id: String::from("code_id"),
+            build_key: format!("build_key_{}", job_id),
+            kind,
+            status,
+            wizard_message: wizard_message(),
+            attempts,
+        }
+    }
+
+    #[test]
+    fn test_restore_jobs_requeues_queued_and_interrupted_jobs() {
+        let queue = CompilationQueue::new();
+        let requeued_builds = queue.restore_jobs(vec![
+            job_record("first", JobKind::Build, JobStatus::Queued, 0),
+            job_record("second", JobKind::Build, JobStatus::Compiling, 1),
+            job_record("third", JobKind::Check, JobStatus::Queued, 0),
+        ]);
+
+        assert_eq!(requeued_builds, vec!["first", "second"]);
+        assert_eq!(queue.get_job("second").unwrap().status, JobStatus::Queued);
+
+        // Jobs keep their ids and the order they were queued in
+        assert_eq!(queue.take_request().unwrap().job_id, "third");
+        assert_eq!(queue.take_request().unwrap().job_id, "first");
+        assert_eq!(queue.take_request().unwrap().job_id, "second");
+    }
+
+    #[test]
+    fn test_restore_jobs_fails_jobs_out_of_attempts() {
+        let queue = CompilationQueue::new();
+        let requeued_builds = queue.restore_jobs(vec![job_record(
+            "crashing",
+            JobKind::Build,
+            JobStatus::Compiling,
+            MAX_JOB_ATTEMPTS,
+        )]);
+
+        assert!(requeued_builds.is_empty());
+        assert!(queue.take_request().is_none());
+        assert_eq!(
+            queue
+                .wait_for_client("crashing")
+                .and_then(|job| job.result()),
+            Err(ServerError::new(String::from(
+                "Compilation interrupted by a server restart."
+            )))
+        );
+    }
+
+    fn wizard_message_from(address: &str) -> WizardMessage {
+        WizardMessage {
+            address: address.to_string(),
+            ..wizard_message()
+        }
+    }
+
+    #[test]
+    fn test_add_request_rejected_when_queue_is_full() {
+        let limits = QueueLimits {
+            max_depth: 2,
+            max_per_address: 0,
+            client_wait_secs: 0,
+        };
+        let queue = CompilationQueue::with_limits(limits, 2);
+        for build_key in ["first", "second"] {
+            queue
+                .add_request(
+                    wizard_message(),
+                    String::from("code_id"),
+                    build_key.to_string(),
+                )
+                .unwrap();
+        }
+
+        // No build finished yet, so a minute per build is assumed and 2 workers halve it
+        assert_eq!(
+            queue.add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("third")
+            ),
+            Err(QueueError::QueueFull { retry_after: 30 })
+        );
+
+        // A request for a build already queued adds no work
+        assert!(
+            queue
+                .add_request(
+                    wizard_message(),
+                    String::from("code_id"),
+                    String::from("first")
+                )
+                .unwrap()
+                .attached
+        );
+
+        // Taking a job frees a slot
+        queue.take_request();
+        assert!(queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("third")
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_add_request_rejected_over_address_limit() {
+        let limits = QueueLimits {
+            max_depth: 0,
+            max_per_address: 1,
+            client_wait_secs: 0,
+        };
+        let queue = CompilationQueue::with_limits(limits, 1);
+        queue
+            .add_request(
+                wizard_message_from("alice"),
+                String::from("code_id"),
+                String::from("alice_first"),
+            )
+            .unwrap();
+        queue
+            .add_request(
+                wizard_message_from("bob"),
+                String::from("code_id"),
+                String::from("bob_first"),
+            )
+            .unwrap();
+
+        // The job of alice is first in the queue, so it is done after one build
+        assert_eq!(
+            queue.add_request(
+                wizard_message_from("alice"),
+                String::from("code_id"),
+                String::from("alice_second")
+            ),
+            Err(QueueError::AddressLimit { retry_after: 60 })
+        );
+        assert!(queue
+            .add_request(
+                wizard_message_from("carol"),
+                String::from("code_id"),
+                String::from("carol_first")
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_estimate_wait_uses_recent_builds() {
+        let queue = CompilationQueue::with_limits(QueueLimits::unlimited(), 2);
+        assert_eq!(queue.estimate_wait(4), 120);
+
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+        for duration_ms in [10_000, 30_000] {
+            queue.set_job_metrics(
+                &job_id,
+                BuildMetrics {
+                    warm_cache: true,
+                    duration_ms,
+                },
+            );
+        }
+
+        assert_eq!(queue.average_build_duration(), Duration::from_secs(20));
+        assert_eq!(queue.estimate_wait(4), 40);
+        assert_eq!(queue.estimate_wait(0), 1);
+    }
+
+    #[test]
+    fn test_get_job_reports_queue_position_and_start() {
+        let queue = CompilationQueue::with_limits(QueueLimits::unlimited(), 1);
+        let mut job_ids = Vec::new();
+        for build_key in ["first", "second", "third"] {
+            let job_id = queue
+                .add_request(
+                    wizard_message(),
+                    String::from("code_id"),
+                    build_key.to_string(),
+                )
+                .unwrap()
+                .job_id;
+            job_ids.push(job_id);
+        }
+        let check_id = queue
+            .add_check_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("check"),
+            )
+            .unwrap()
+            .job_id;
+
+        // The only worker is idle, so the check it takes first starts right away
+        let check = queue.get_job(&check_id).unwrap();
+        assert_eq!(check.queue_position, Some(1));
+        assert_eq!(check.estimated_start_secs, Some(0));
+
+        let third = queue.get_job(&job_ids[2]).unwrap();
+        assert_eq!(third.queue_position, Some(4));
+        assert_eq!(third.estimated_start_secs, Some(180));
+
+        // Once taken, the job has no position anymore and the others move up
+        queue.take_request();
+        queue.take_request();
+        let first = queue.get_job(&job_ids[0]).unwrap();
+        assert_eq!(first.queue_position, None);
+        assert_eq!(first.estimated_start_secs, None);
+
+        let third = queue.get_job(&job_ids[2]).unwrap();
+        assert_eq!(third.queue_position, Some(2));
+        assert_eq!(third.estimated_start_secs, Some(120));
+    }
+
+    #[test]
+    fn test_summary() {
+        let queue = CompilationQueue::with_limits(QueueLimits::unlimited(), 2);
+        assert_eq!(
+            queue.summary(),
+            QueueSummary {
+                depth: 0,
+                active_builds: 0,
+                workers: 2,
+                average_build_ms: None,
+                average_wait_ms: None,
+            }
+        );
+
+        for build_key in ["first", "second"] {
+            queue
+                .add_request(
+                    wizard_message(),
+                    String::from("code_id"),
+                    build_key.to_string(),
+                )
+                .unwrap();
+        }
+        let request = queue.take_request().unwrap();
+        queue.set_job_metrics(
+            &request.job_id,
+            BuildMetrics {
+                warm_cache: false,
+                duration_ms: 5000,
+            },
+        );
+
+        let summary = queue.summary();
+        assert_eq!(summary.depth, 1);
+        assert_eq!(summary.active_builds, 1);
+        assert_eq!(summary.average_build_ms, Some(5000));
+        assert!(summary.average_wait_ms.is_some());
+    }
+
+    #[test]
+    fn test_add_request_attaches_to_running_job() {
+        let queue = CompilationQueue::new();
+        let first = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        let second = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        let other = queue
+            .add_request(
+                wizard_message(),
+                String::from("other_code_id"),
+                String::from("other_build_key"),
+            )
+            .unwrap();
+
+        assert!(!first.attached);
+        assert!(second.attached);
+        assert_eq!(second.job_id, first.job_id);
+        assert!(!other.attached);
+        assert_ne!(other.job_id, first.job_id);
+
+        // A single build runs for both requests of the same code_id
+        assert_eq!(queue.take_request().unwrap().job_id, first.job_id);
+        assert_eq!(queue.take_request().unwrap().job_id, other.job_id);
+        assert!(queue.take_request().is_none());
+
+        // The attached request still joins the job while it is compiling
+        let third = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        assert_eq!(third.job_id, first.job_id);
+    }
+
+    #[test]
+    fn test_add_request_same_code_other_build_key_creates_new_job() {
+        let queue = CompilationQueue::new();
+        let first = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        let second = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("other_build_key"),
+            )
+            .unwrap();
+
+        assert!(!second.attached);
+        assert_ne!(second.job_id, first.job_id);
+    }
+
+    #[test]
+    fn test_add_request_after_job_finished_creates_new_job() {
+        let queue = CompilationQueue::new();
+        let first = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        queue.finish_job(
+            &first.job_id,
+            Err(ServerError::new(String::from("Error compiling contract."))),
+        );
+
+        let second = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+
+        assert!(!second.attached);
+        assert_ne!(second.job_id, first.job_id);
+    }
+
+    #[test]
+    fn test_check_requests_are_taken_before_builds() {
+        let queue = CompilationQueue::new();
+        let build = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        let check = queue
+            .add_check_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+
+        // A check never joins the build of the same code, nor the other way around
+        assert!(!check.attached);
+        assert_ne!(check.job_id, build.job_id);
+
+        let request = queue.take_request().unwrap();
+        assert_eq!(request.job_id, check.job_id);
+        assert_eq!(request.kind, JobKind::Check);
+        assert_eq!(queue.take_request().unwrap().job_id, build.job_id);
+    }
+
+    #[test]
+    fn test_finish_check_job_keeps_diagnostics() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_check_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        queue.finish_check_job(&job_id, Ok(vec![]));
+
+        let job = queue.wait_for_client(&job_id).unwrap();
+        assert_eq!(job.kind, JobKind::Check);
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.diagnostics, Some(vec![]));
+        assert!(job.contract.is_none());
+    }
+
+    #[test]
+    fn test_finish_test_job_keeps_report() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_test_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        queue.finish_test_job(&job_id, Ok(TestReport::new(vec![])));
+
+        let job = queue.wait_for_client(&job_id).unwrap();
+        assert_eq!(job.kind, JobKind::Test);
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.test_report, Some(TestReport::new(vec![])));
+    }
+
+    #[test]
+    fn test_unknown_job() {
+        let queue = CompilationQueue::new();
+        assert!(queue.get_job("unknown").is_none());
+    }
+
+    #[test]
+    fn test_cancel_queued_job_removes_it_from_the_queue() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        let job = queue.cancel_job(&job_id).unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(queue.take_request().is_none());
+        assert_eq!(
+            queue.wait_for_client(&job_id).and_then(|job| job.result()),
+            Err(ServerError::new(String::from("Job cancelled.")))
+        );
+
+        assert_eq!(queue.cancel_job(&job_id), Err(CancelError::Finished));
+        assert_eq!(queue.cancel_job("unknown"), Err(CancelError::NotFound));
+    }
+
+    #[test]
+    fn test_cancel_compiling_job_sets_the_worker_flag() {
+        let queue = CompilationQueue::new();
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        let cancelled = Arc::new(AtomicBool::new(true));
+        let request = queue.take_request().unwrap();
+        assert!(queue.watch_running_job(&request.job_id, &cancelled));
+        assert!(!cancelled.load(Ordering::Relaxed));
+
+        queue.cancel_job(&job_id).unwrap();
+        assert!(cancelled.load(Ordering::Relaxed));
+
+        // The worker finishing the killed build doesn't override the cancellation
+        queue.finish_job(
+            &job_id,
+            Err(ServerError::new(String::from("Error compiling contract."))),
+        );
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Cancelled);
+        assert!(!queue.watch_running_job(&job_id, &cancelled));
+    }
+
+    #[test]
+    fn test_job_cancelled_when_the_last_waiter_gives_up() {
+        let limits = QueueLimits {
+            max_depth: 0,
+            max_per_address: 0,
+            client_wait_secs: 1,
+        };
+        let queue = CompilationQueue::with_limits(limits, 1);
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        assert_eq!(
+            queue.wait_for_client(&job_id),
+            Err(ServerError::new(String::from(
+                "Timed out waiting for the job."
+            )))
+        );
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Cancelled);
+        assert!(queue.take_request().is_none());
+    }
+
+    #[test]
+    fn test_release_job_cancels_it_once_nobody_polls_it() {
+        let queue = CompilationQueue::new();
+        let first = queue
+            .add_polled_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        let attached = queue
+            .add_polled_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        assert!(attached.attached);
+        assert_eq!(attached.job_id, first.job_id);
+
+        let job_id = first.job_id;
+        let first_token = first.release_token.unwrap();
+        let attached_token = attached.release_token.unwrap();
+        assert_ne!(first_token, attached_token);
+
+        // The other client still polls the job, so it keeps running for it
+        assert_eq!(
+            queue.release_job(&job_id, &first_token).unwrap().status,
+            JobStatus::Queued
+        );
+        assert_eq!(
+            queue.release_job(&job_id, &attached_token).unwrap().status,
+            JobStatus::Cancelled
+        );
+        assert!(queue.take_request().is_none());
+        assert_eq!(
+            queue.release_job(&job_id, &attached_token),
+            Err(CancelError::Finished)
+        );
+    }
+
+    #[test]
+    fn test_release_job_only_takes_each_token_once() {
+        let queue = CompilationQueue::new();
+        let first = queue
+            .add_polled_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        queue
+            .add_polled_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+
+        let job_id = first.job_id;
+        let first_token = first.release_token.unwrap();
+
+        // Releasing again, or with a made up token, doesn't drop the other client
+        assert_eq!(
+            queue.release_job(&job_id, "made up token"),
+            Err(CancelError::InvalidToken)
+        );
+        queue.release_job(&job_id, &first_token).unwrap();
+        assert_eq!(
+            queue.release_job(&job_id, &first_token),
+            Err(CancelError::InvalidToken)
+        );
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Queued);
+
+        // Blocking requests don't get a token, they can't release the job
+        let blocking = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+        assert_eq!(blocking.release_token, None);
+    }
+
+    #[test]
+    fn test_polled_job_kept_when_the_last_waiter_gives_up() {
+        let limits = QueueLimits {
+            max_depth: 0,
+            max_per_address: 0,
+            client_wait_secs: 1,
+        };
+        let queue = CompilationQueue::with_limits(limits, 1);
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+        queue
+            .add_polled_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap();
+
+        assert!(queue.wait_for_client(&job_id).is_err());
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Queued);
+        assert_eq!(queue.take_request().unwrap().job_id, job_id);
+    }
+
+    #[test]
+    fn test_wait_for_request_wakes_up_when_a_request_is_queued() {
+        let queue = Arc::new(CompilationQueue::new());
+        let shutdown_flag = Arc::new(AtomicBool::new(false));
+
+        let worker_queue = queue.clone();
+        let worker_flag = shutdown_flag.clone();
+        let worker = thread::spawn(move || worker_queue.wait_for_request(&worker_flag));
+
+        thread::sleep(Duration::from_millis(100));
+        let job_id = queue
+            .add_request(
+                wizard_message(),
+                String::from("code_id"),
+                String::from("build_key"),
+            )
+            .unwrap()
+            .job_id;
+
+        assert_eq!(worker.join().unwrap().unwrap().job_id, job_id);
+        assert_eq!(queue.get_job(&job_id).unwrap().status, JobStatus::Compiling);
+    }
+
+    #[test]
+    fn test_wait_for_request_returns_on_shutdown() {
+        let queue = Arc::new(CompilationQueue::new());
+        let shutdown_flag = Arc::new(AtomicBool::new(false));
+
+        let worker_queue = queue.clone();
+        let worker_flag = shutdown_flag.clone();
+        let worker = thread::spawn(move || worker_queue.wait_for_request(&worker_flag));
+
+        thread::sleep(Duration::from_millis(100));
+        shutdown_flag.store(true, Ordering::Relaxed);
+        queue.wake_workers();
+
+        assert!(worker.join().unwrap().is_none());
+    }
+}
@@ -16,31 +16,191 @@ mod compiler_test {
         let queue = CompilationQueue::new();
         let compilation_queue = Arc::new(queue);
         let shutdown_flag = Arc::new(AtomicBool::new(false));
-        let compiler = Compiler::init(compilation_queue, shutdown_flag.clone());
+        let toolchains = Arc::new(Toolchains::init());
+        let sandbox = Arc::new(Sandbox::init());
+        let compiler = Compiler::init(
+            0,
+            toolchains,
+            sandbox,
+            compilation_queue,
+            shutdown_flag.clone(),
+        );
+        let profile = compiler.toolchains.default_profile().unwrap();
 
         let wizard_message = WizardMessage {
             address: "ABC".to_string(),
             code: LIB_RS_CODE.to_string(),
             features: vec!["psp22".to_string(), "ownable".to_string()],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         // Create lib.rs file
-        compiler
-            .create_contract_files(&wizard_message)
+        let manifest = compiler
+            .create_contract_files(profile, &wizard_message)
             .expect("Could not create lib.rs file");
 
         // Compile contract
-        let result = compiler.compile_contract();
+        let target_dir = compiler.target_dir(profile, &manifest);
+        let result = compiler.compile_contract(profile, &target_dir, &wizard_message.build_options);
 
         // Check if the contract was compiled successfully
         assert!(result.is_ok());
+        assert!(compiler
+            .get_contract_data(profile, &target_dir, "ABC", "ABC")
+            .is_ok());
 
         // Delete test compilation
-        compiler.delete_compilation_files();
+        std::fs::remove_dir_all(&target_dir).expect("Error deleting target dir");
 
         // Delete test file
-        let file_path = compiler.dir_path.join("lib.rs");
+        let file_path = compiler.workspace_path(profile).join("lib.rs");
         remove_file(&file_path).expect("Error deleting file");
+        let file_path = compiler.workspace_path(profile).join("Cargo.toml");
+        remove_file(&file_path).expect("Error deleting file");
+    }
+
+    #[test]
+    fn create_workspace_copies_template_crate() {
+        let template_path = env::current_dir().unwrap().join("compilation_target");
+        let dir_path = env::temp_dir().join("compiler_be_test_workspace");
+
+        Compiler::create_workspace(&template_path, None, &dir_path)
+            .expect("Error creating workspace");
+
+        assert!(dir_path.join(MANIFEST_TEMPLATE).exists());
+        assert!(dir_path.join("template-lib.rs").exists());
+        assert!(!dir_path.join("lib.rs").exists());
+
+        std::fs::remove_dir_all(&dir_path).expect("Error deleting workspace");
+    }
+
+    #[test]
+    fn create_workspace_uses_vendored_sources() {
+        let template_path = env::current_dir().unwrap().join("compilation_target");
+        let vendor_path = env::temp_dir().join("compiler_be_test_workspace_vendor");
+        let dir_path = env::temp_dir().join("compiler_be_test_vendored_workspace");
+
+        std::fs::create_dir_all(&vendor_path).unwrap();
+        std::fs::write(vendor_path.join(VENDOR_LOCKFILE), "version = 3\n").unwrap();
+        std::fs::write(
+            vendor_path.join(VENDOR_CONFIG),
+            "[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n[source.vendored-sources]\ndirectory = \"vendor\"\n",
+        )
+        .unwrap();
+
+        Compiler::create_workspace(&template_path, Some(&vendor_path), &dir_path)
+            .expect("Error creating workspace");
+
+        let lockfile = read_to_string(dir_path.join("Cargo.lock")).unwrap();
+        let cargo_config = read_to_string(dir_path.join(".cargo").join("config.toml")).unwrap();
+
+        std::fs::remove_dir_all(&vendor_path).expect("Error deleting vendor dir");
+        std::fs::remove_dir_all(&dir_path).expect("Error deleting workspace");
+
+        assert_eq!(lockfile, "version = 3\n");
+        assert!(cargo_config.contains(&vendor_path.display().to_string()));
+        assert!(cargo_config.contains("offline = true"));
+    }
+
+    #[test]
+    fn remove_stale_outputs_keeps_dependency_artifacts() {
+        let workspace_path = env::temp_dir().join("compiler_be_test_stale_outputs");
+        let target_dir = workspace_path.join("targets").join("features");
+        let deps_dir = target_dir.join("ink").join("release").join("deps");
+
+        std::fs::create_dir_all(&deps_dir).unwrap();
+        std::fs::create_dir_all(workspace_path.join("__openbrush_metadata_folder")).unwrap();
+        std::fs::write(deps_dir.join("libink.rlib"), "").unwrap();
+        for output in CONTRACT_OUTPUTS {
+            std::fs::write(target_dir.join("ink").join(output), "").unwrap();
+        }
+
+        Compiler::remove_stale_outputs(&workspace_path, &target_dir);
+
+        let deps_kept = deps_dir.join("libink.rlib").exists();
+        let outputs_left = CONTRACT_OUTPUTS
+            .iter()
+            .any(|output| target_dir.join("ink").join(output).exists());
+        let metadata_left = workspace_path.join("__openbrush_metadata_folder").exists();
+
+        std::fs::remove_dir_all(&workspace_path).expect("Error deleting workspace");
+
+        assert!(deps_kept);
+        assert!(!outputs_left);
+        assert!(!metadata_left);
+    }
+
+    #[test]
+    fn offline_flag_freezes_vendored_profiles() {
+        let mut profile = Toolchains::load_profiles("toolchains.json").unwrap()[0].clone();
+
+        profile.vendor_dir = None;
+        assert_eq!(offline_flag(&profile), "--offline");
+
+        profile.vendor_dir = Some(String::from("vendor/ink4"));
+        assert_eq!(offline_flag(&profile), "--frozen");
+    }
+
+    #[test]
+    fn evict_target_dirs_removes_least_recently_used() {
+        let targets_path = env::temp_dir().join("compiler_be_test_evict_targets");
+        let now = SystemTime::now();
+
+        // oldest is the least recently used, in_use is the oldest but it is about to be built
+        for (name, age) in [("in_use", 40), ("oldest", 30), ("old", 20), ("recent", 10)] {
+            let dir = targets_path.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            File::open(&dir)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        evict_target_dirs(&targets_path, &targets_path.join("in_use"), 3).unwrap();
+
+        let mut left: Vec<String> = read_dir(&targets_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+
+        std::fs::remove_dir_all(&targets_path).expect("Error deleting targets dir");
+
+        assert_eq!(left, vec!["in_use", "old", "recent"]);
+    }
+
+    #[test]
+    fn contract_build_args_follow_build_options() {
+        let mut profile = Toolchains::load_profiles("toolchains.json").unwrap()[0].clone();
+        profile.vendor_dir = None;
+
+        let release = contract_build_args(&profile, &BuildOptions::default());
+        assert!(release.contains(&String::from("--release")));
+        assert!(release.contains(&String::from("--offline")));
+        assert!(!release.iter().any(|arg| arg.starts_with("--features")));
+
+        let debug = contract_build_args(
+            &profile,
+            &BuildOptions {
+                mode: BuildMode::Debug,
+                optimization_passes: Some(String::from("z")),
+                keep_debug_symbols: true,
+                features: vec![String::from("std"), String::from("ink-as-dependency")],
+            },
+        );
+        assert!(!debug.contains(&String::from("--release")));
+        assert!(debug.contains(&String::from("--optimization-passes=z")));
+        assert!(debug.contains(&String::from("--keep-debug-symbols")));
+        assert!(debug.contains(&String::from("--features=std,ink-as-dependency")));
+
+        // The vendored Cargo.lock is never rewritten by a build either
+        profile.vendor_dir = Some(String::from("vendor/ink4"));
+        let vendored = contract_build_args(&profile, &BuildOptions::default());
+        assert!(vendored.contains(&String::from("--frozen")));
+        assert!(!vendored.contains(&String::from("--offline")));
     }
 
     const LIB_RS_CODE: &str = "#![cfg_attr(not(feature = \"std\"), no_std)]
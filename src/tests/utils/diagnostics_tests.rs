@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod diagnostics_test {
+    use super::super::*;
+
+    const UNRESOLVED_VALUE: &str = r#"{"reason":"compiler-message","package_id":"compiled_contract 1.0.0 (path+file:///compilation_workspaces/worker_0)","manifest_path":"/compilation_workspaces/worker_0/Cargo.toml","target":{"kind":["cdylib"],"crate_types":["cdylib"],"name":"compiled_contract","src_path":"/compilation_workspaces/worker_0/lib.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"error[E0425]: cannot find value `supply` in this scope","children":[],"code":{"code":"E0425","explanation":"An unresolved name was used."},"level":"error","message":"cannot find value `supply` in this scope","spans":[{"byte_end":712,"byte_start":706,"column_end":50,"column_start":44,"expansion":null,"file_name":"lib.rs","is_primary":true,"label":"not found in this scope","line_end":28,"line_start":28,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"#;
+
+    const MACRO_WARNING: &str = r##"{"reason":"compiler-message","package_id":"compiled_contract 1.0.0 (path+file:///compilation_workspaces/worker_0)","manifest_path":"/compilation_workspaces/worker_0/Cargo.toml","target":{"kind":["cdylib"],"crate_types":["cdylib"],"name":"compiled_contract","src_path":"/compilation_workspaces/worker_0/lib.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"warning: unused variable","children":[],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `value`","spans":[{"byte_end":10,"byte_start":5,"column_end":10,"column_start":5,"expansion":{"def_site_span":{"byte_end":0,"byte_start":0,"column_end":1,"column_start":1,"expansion":null,"file_name":"<::ink::contract macros>","is_primary":false,"label":null,"line_end":1,"line_start":1,"suggested_replacement":null,"suggestion_applicability":null,"text":[]},"macro_decl_name":"#[ink::contract]","span":{"byte_end":120,"byte_start":100,"column_end":22,"column_start":1,"expansion":null,"file_name":"lib.rs","is_primary":false,"label":null,"line_end":4,"line_start":4,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}},"file_name":"<::ink::contract macros>","is_primary":true,"label":null,"line_end":1,"line_start":1,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"##;
+
+    const ABORTING: &str = r#"{"reason":"compiler-message","package_id":"compiled_contract 1.0.0 (path+file:///compilation_workspaces/worker_0)","manifest_path":"/compilation_workspaces/worker_0/Cargo.toml","target":{"kind":["cdylib"],"crate_types":["cdylib"],"name":"compiled_contract","src_path":"/compilation_workspaces/worker_0/lib.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"error: aborting due to previous error","children":[],"code":null,"level":"error","message":"aborting due to previous error","spans":[]}}"#;
+
+    const DEPENDENCY_WARNING: &str = r#"{"reason":"compiler-message","package_id":"openbrush 3.0.0","manifest_path":"/openbrush/Cargo.toml","target":{"kind":["lib"],"crate_types":["lib"],"name":"openbrush","src_path":"/openbrush/lib.rs","edition":"2021","doc":true,"doctest":false,"test":true},"message":{"rendered":"warning: unused import","children":[],"code":{"code":"unused_imports","explanation":null},"level":"warning","message":"unused import","spans":[{"byte_end":10,"byte_start":5,"column_end":10,"column_start":5,"expansion":null,"file_name":"lib.rs","is_primary":true,"label":null,"line_end":1,"line_start":1,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"#;
+
+    const BUILD_FINISHED: &str = r#"{"reason":"build-finished","success":false}"#;
+
+    #[test]
+    fn test_parse_error_with_span() {
+        let diagnostics = parse_cargo_messages(UNRESOLVED_VALUE);
+
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                severity: DiagnosticSeverity::Error,
+                message: String::from("cannot find value `supply` in this scope"),
+                code: Some(String::from("E0425")),
+                span: Some(DiagnosticSpan {
+                    line_start: 28,
+                    line_end: 28,
+                    column_start: 44,
+                    column_end: 50,
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_macro_expansion_points_to_call_site() {
+        let diagnostics = parse_cargo_messages(MACRO_WARNING);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[0].code, Some(String::from("unused_variables")));
+        assert_eq!(
+            diagnostics[0].span,
+            Some(DiagnosticSpan {
+                line_start: 4,
+                line_end: 4,
+                column_start: 1,
+                column_end: 22,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_skips_summaries_dependencies_and_other_messages() {
+        let output = [
+            DEPENDENCY_WARNING,
+            UNRESOLVED_VALUE,
+            "not json at all",
+            ABORTING,
+            BUILD_FINISHED,
+        ]
+        .join("\n");
+
+        let diagnostics = parse_cargo_messages(&output);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some(String::from("E0425")));
+    }
+
+    #[test]
+    fn test_parse_empty_output() {
+        assert!(parse_cargo_messages("").is_empty());
+    }
+}
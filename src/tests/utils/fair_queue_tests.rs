@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod fair_queue_test {
+    use super::super::*;
+    use crate::models::api_models::WizardMessage;
+    use crate::models::db_models::JobKind;
+    use std::time::Instant;
+
+    fn request(job_id: &str, address: &str, priority: JobPriority) -> CompilationRequest {
+        CompilationRequest {
+            wizard_message: WizardMessage {
+                address: address.to_string(),
+                code: String::from("code"),
+                features: vec![String::from("psp22")],
+                toolchain: None,
+                build_options: Default::default(),
+                lint: false,
+                priority: Some(priority),
+            },
+            code_id: String::from("code_id"),
+            build_key: job_id.to_string(),
+            kind: JobKind::Build,
+            priority,
+            job_id: job_id.to_string(),
+            queued_at: Instant::now(),
+        }
+    }
+
+    fn pop_order(queue: &mut FairQueue) -> Vec<String> {
+        queue
+            .drain()
+            .into_iter()
+            .map(|request| request.job_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_addresses_take_turns() {
+        let mut queue = FairQueue::new();
+        for job_id in ["a1", "a2", "a3"] {
+            queue.push(request(job_id, "alice", JobPriority::Normal));
+        }
+        queue.push(request("b1", "bob", JobPriority::Normal));
+        queue.push(request("c1", "carol", JobPriority::Normal));
+        queue.push(request("b2", "bob", JobPriority::Normal));
+
+        assert_eq!(queue.len(), 6);
+        assert_eq!(pop_order(&mut queue), ["a1", "b1", "c1", "a2", "b2", "a3"]);
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_higher_priority_classes_go_first() {
+        let mut queue = FairQueue::new();
+        queue.push(request("low", "alice", JobPriority::Low));
+        queue.push(request("normal", "alice", JobPriority::Normal));
+        queue.push(request("high", "bob", JobPriority::High));
+
+        assert_eq!(pop_order(&mut queue), ["high", "normal", "low"]);
+    }
+
+    #[test]
+    fn test_address_back_in_line_after_emptying() {
+        let mut queue = FairQueue::new();
+        queue.push(request("a1", "alice", JobPriority::Normal));
+        queue.push(request("b1", "bob", JobPriority::Normal));
+        assert_eq!(queue.pop().unwrap().job_id, "a1");
+
+        // alice had no requests left, so her new one waits for bob's turn
+        queue.push(request("a2", "alice", JobPriority::Normal));
+        assert_eq!(pop_order(&mut queue), ["b1", "a2"]);
+    }
+
+    #[test]
+    fn test_requests_ahead_follows_pop_order() {
+        let mut queue = FairQueue::new();
+        for (job_id, address) in [
+            ("a1", "alice"),
+            ("a2", "alice"),
+            ("a3", "alice"),
+            ("b1", "bob"),
+            ("c1", "carol"),
+            ("c2", "carol"),
+        ] {
+            queue.push(request(job_id, address, JobPriority::Normal));
+        }
+        queue.push(request("h1", "dave", JobPriority::High));
+        queue.push(request("l1", "bob", JobPriority::Low));
+
+        let ahead: Vec<(String, usize)> = ["a1", "a2", "a3", "b1", "c1", "c2", "h1", "l1"]
+            .iter()
+            .map(|job_id| (job_id.to_string(), queue.requests_ahead(job_id).unwrap()))
+            .collect();
+        assert!(queue.requests_ahead("unknown").is_none());
+
+        for (position, job_id) in pop_order(&mut queue).iter().enumerate() {
+            assert!(ahead.contains(&(job_id.clone(), position)));
+        }
+    }
+
+    #[test]
+    fn test_remove_keeps_the_other_turns() {
+        let mut queue = FairQueue::new();
+        for (job_id, address) in [
+            ("a1", "alice"),
+            ("a2", "alice"),
+            ("b1", "bob"),
+            ("c1", "carol"),
+        ] {
+            queue.push(request(job_id, address, JobPriority::Normal));
+        }
+
+        assert_eq!(queue.remove("b1").unwrap().job_id, "b1");
+        assert!(queue.remove("b1").is_none());
+        assert_eq!(queue.len(), 3);
+
+        assert_eq!(pop_order(&mut queue), ["a1", "c1", "a2"]);
+    }
+}
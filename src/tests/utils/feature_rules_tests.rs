@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod feature_rules_test {
+    use super::super::*;
+
+    fn features(features: &[&str]) -> Vec<String> {
+        features.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_catalog_rules_accept_valid_features() {
+        let rules = FeatureRules::from_catalog();
+
+        assert!(rules
+            .violations(&features(&["psp22", "mintable", "capped", "ownable"]))
+            .is_empty());
+        assert!(rules
+            .violations(&features(&["psp37", "batch", "enumerable"]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_catalog_rules_report_every_violation() {
+        let rules = FeatureRules::from_catalog();
+
+        assert_eq!(
+            rules.violations(&features(&["capped", "proxy", "diamond"])),
+            vec![
+                "Features must contain at least one contract standard",
+                "Feature capped requires psp22",
+                "Feature diamond conflicts with proxy",
+            ]
+        );
+        assert_eq!(
+            rules.violations(&features(&[
+                "psp34", "psp37", "capped", "wrapper", "mintable"
+            ])),
+            vec![
+                "Feature contains ambiguous contract standard",
+                "Feature mintable conflicts with wrapper",
+                "Feature capped requires psp22",
+                "Feature wrapper requires psp22",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rules_loaded_from_config() {
+        let config: Vec<FeatureRule> = serde_json::from_str(
+            r#"[
+                { "rule": "conflicts", "features": ["ownable", "access-control"] },
+                { "rule": "requires", "feature": "pausable", "any_of": ["ownable", "access-control"], "reason": "Pausable contracts need an owner or an admin" },
+                { "rule": "conflicts", "features": ["psp22", "psp34", "psp37"] }
+            ]"#,
+        )
+        .unwrap();
+        let rules = FeatureRules { rules: config };
+
+        assert_eq!(
+            rules.violations(&features(&["psp22", "pausable"])),
+            vec!["Pausable contracts need an owner or an admin"]
+        );
+        assert_eq!(
+            rules.violations(&features(&["psp22", "psp34", "ownable", "access-control"])),
+            vec![
+                "Feature ownable conflicts with access-control",
+                "Feature psp22 conflicts with psp34",
+            ]
+        );
+        assert_eq!(
+            rules.violations(&features(&["psp22", "psp34", "psp37"])),
+            vec!["Features psp22, psp34, psp37 can't be used together"]
+        );
+    }
+
+    #[test]
+    fn test_load_rules_rejects_unknown_features() {
+        let config_path = std::env::temp_dir().join("feature_rules_unknown.json");
+        std::fs::write(
+            &config_path,
+            r#"[{ "rule": "requires", "feature": "psp22", "any_of": ["teleport"] }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            FeatureRules::load_rules(config_path.to_str().unwrap()),
+            Err(String::from("Unknown feature in rules: teleport"))
+        );
+        std::fs::remove_file(config_path).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod manifest_test {
+    use super::super::*;
+
+    const TEMPLATE: &str = "openbrush = { tag = \"3.0.0\", features = [{{openbrush_features}}] }";
+
+    fn features(features: &[&str]) -> Vec<String> {
+        features.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_openbrush_features_are_mapped_sorted_and_deduplicated() {
+        let result =
+            openbrush_features(&features(&["psp22", "access-control", "pausable", "psp22"]));
+
+        assert_eq!(result, vec!["access_control", "pausable", "psp22"]);
+    }
+
+    #[test]
+    fn test_extensions_of_a_standard_enable_no_openbrush_feature() {
+        let result = openbrush_features(&features(&["psp22", "mintable", "capped", "timelock"]));
+
+        assert_eq!(result, vec!["psp22", "timelock_controller"]);
+    }
+
+    #[test]
+    fn test_render_manifest_only_enables_requested_features() {
+        let manifest = render_manifest(TEMPLATE, &features(&["psp34", "ownable"]));
+
+        assert_eq!(
+            manifest,
+            "openbrush = { tag = \"3.0.0\", features = [\"ownable\", \"psp34\"] }"
+        );
+    }
+
+    #[test]
+    fn test_render_manifest_is_stable_for_the_same_features() {
+        let manifest_a = render_manifest(TEMPLATE, &features(&["psp22", "pausable"]));
+        let manifest_b = render_manifest(TEMPLATE, &features(&["pausable", "psp22"]));
+
+        assert_eq!(manifest_a, manifest_b);
+    }
+
+    #[test]
+    fn test_template_manifest_contains_placeholder() {
+        let template = std::fs::read_to_string(
+            std::env::current_dir()
+                .unwrap()
+                .join("compilation_target")
+                .join(MANIFEST_TEMPLATE),
+        )
+        .unwrap();
+
+        assert!(template.contains(FEATURES_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_manifest_key_changes_with_features() {
+        let template = "features = [{{openbrush_features}}]";
+        let psp22 = render_manifest(template, &[String::from("psp22")]);
+        let psp34 = render_manifest(template, &[String::from("psp34")]);
+
+        assert_eq!(
+            manifest_key(&psp22),
+            manifest_key(&render_manifest(template, &[String::from("psp22")]))
+        );
+        assert_ne!(manifest_key(&psp22), manifest_key(&psp34));
+        assert_eq!(manifest_key(&psp22).len(), 16);
+    }
+}
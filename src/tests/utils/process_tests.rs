@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod process_test {
+    use super::super::*;
+
+    #[test]
+    fn test_run_cancellable_captures_output() {
+        let output = run_cancellable(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            Duration::from_secs(5),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "out\n");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "err\n");
+    }
+
+    #[test]
+    fn test_run_cancellable_kills_the_process_group() {
+        let started = Instant::now();
+
+        // The background sleep keeps the pipes open unless the whole group is killed
+        let result = run_cancellable(
+            Command::new("sh").args(["-c", "sleep 30 & sleep 30"]),
+            Duration::from_millis(300),
+            &AtomicBool::new(false),
+        );
+
+        assert!(matches!(result, Err(RunError::TimedOut)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_run_cancellable_kills_the_process_when_cancelled() {
+        let started = Instant::now();
+        let cancelled = std::sync::Arc::new(AtomicBool::new(false));
+
+        let flag = cancelled.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            flag.store(true, Ordering::Relaxed);
+        });
+
+        let result = run_cancellable(
+            Command::new("sh").args(["-c", "sleep 30 & sleep 30"]),
+            Duration::from_secs(60),
+            &cancelled,
+        );
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(RunError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_run_cancellable_missing_binary() {
+        let result = run_cancellable(
+            &mut Command::new("this-binary-does-not-exist"),
+            Duration::from_secs(1),
+            &AtomicBool::new(false),
+        );
+
+        assert!(matches!(result, Err(RunError::Io(_))));
+    }
+}
@@ -0,0 +1,252 @@
+#[cfg(test)]
+mod sandbox_test {
+    use super::super::*;
+    use std::ffi::OsStr;
+
+    fn sandbox(enabled: bool) -> Sandbox {
+        Sandbox {
+            enabled,
+            bwrap: String::from("bwrap"),
+            memory_limit_mb: 0,
+            cpu_time_limit: 0,
+            max_processes: 0,
+            uid_base: 0,
+            build_uid: None,
+        }
+    }
+
+    // Only root can switch to the build uid, the tests relying on it don't run otherwise
+    fn is_root() -> bool {
+        unsafe { libc::geteuid() == 0 }
+    }
+
+    #[test]
+    fn test_wrap_runs_command_inside_bwrap() {
+        let workspace = Path::new("/compilation_workspaces/worker_0/ink4");
+        let mut command = Command::new("cargo");
+        command
+            .args(["contract", "build"])
+            .env("RUSTUP_TOOLCHAIN", "nightly-2023-02-07")
+            .current_dir(workspace);
+
+        let vendor_dir = PathBuf::from("/srv/vendor/ink4");
+        let wrapped = sandbox(true).wrap(command, workspace, std::slice::from_ref(&vendor_dir));
+        let args: Vec<&OsStr> = wrapped.get_args().collect();
+        let binds = |source: &OsStr| {
+            args.windows(3).any(|w| {
+                w[0].to_str()
+                    .is_some_and(|bind| bind.starts_with("--ro-bind"))
+                    && w[1] == source
+                    && w[2] == source
+            })
+        };
+
+        assert_eq!(wrapped.get_program(), "bwrap");
+        assert!(args.contains(&OsStr::new("--unshare-all")));
+        assert!(binds(OsStr::new("/usr")));
+        assert!(binds(vendor_dir.as_os_str()));
+        // The host root, with the server config and secrets, is not visible
+        assert!(!binds(OsStr::new("/")));
+        assert!(args.windows(3).any(|w| w
+            == [
+                OsStr::new("--bind"),
+                workspace.as_os_str(),
+                workspace.as_os_str()
+            ]));
+        assert!(args.ends_with(&[
+            OsStr::new("--"),
+            OsStr::new("cargo"),
+            OsStr::new("contract"),
+            OsStr::new("build")
+        ]));
+        assert_eq!(wrapped.get_current_dir(), Some(workspace));
+        assert!(wrapped
+            .get_envs()
+            .any(|(key, value)| key == "RUSTUP_TOOLCHAIN"
+                && value == Some(OsStr::new("nightly-2023-02-07"))));
+    }
+
+    #[test]
+    fn test_wrap_disabled_keeps_command() {
+        let mut command = Command::new("cargo");
+        command.args(["contract", "build"]);
+
+        let wrapped = sandbox(false).wrap(command, Path::new("/tmp"), &[]);
+        let args: Vec<&OsStr> = wrapped.get_args().collect();
+
+        assert_eq!(wrapped.get_program(), "cargo");
+        assert_eq!(args, vec![OsStr::new("contract"), OsStr::new("build")]);
+    }
+
+    #[test]
+    fn test_wrap_applies_resource_limits() {
+        let mut limited = sandbox(false);
+        limited.memory_limit_mb = 1024;
+        limited.cpu_time_limit = 60;
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -v; ulimit -t"]);
+
+        let output = limited
+            .wrap(command, Path::new("/tmp"), &[])
+            .output()
+            .unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "1048576\n60\n");
+    }
+
+    #[test]
+    fn test_wrap_clears_server_environment() {
+        let mut command = Command::new("env");
+        command.env("RUSTUP_TOOLCHAIN", "nightly-2023-02-07");
+
+        let output = sandbox(false)
+            .wrap(command, Path::new("/tmp"), &[])
+            .output()
+            .unwrap();
+        let vars = String::from_utf8_lossy(&output.stdout);
+
+        // cargo runs the tests with CARGO_PKG_NAME and others set, none of them reach the command
+        assert!(vars.contains("RUSTUP_TOOLCHAIN=nightly-2023-02-07"));
+        for var in vars.lines() {
+            let key = var.split('=').next().unwrap();
+            assert!(ALLOWED_ENV_VARS.contains(&key), "{} was kept", key);
+        }
+    }
+
+    #[test]
+    fn test_sandboxed_command_only_sees_the_workspace() {
+        let mut check = Command::new("bwrap");
+        if check.arg("--version").output().is_err() {
+            eprintln!("bwrap is not installed, skipping");
+            return;
+        }
+
+        let workspace = env::temp_dir().join("compiler_be_test_bwrap");
+        std::fs::create_dir_all(&workspace).unwrap();
+        let server_dir = env::current_dir().unwrap();
+
+        // The workspace is writable and the server dir is not there
+        let mut command = Command::new("sh");
+        command
+            .args([
+                "-c",
+                &format!("echo built > out && ! test -e {}", server_dir.display()),
+            ])
+            .current_dir(&workspace);
+
+        let output = sandbox(true)
+            .wrap(command, &workspace, &[])
+            .output()
+            .unwrap();
+        let written = std::fs::read_to_string(workspace.join("out"));
+
+        std::fs::remove_dir_all(&workspace).expect("Error deleting workspace");
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(written.unwrap(), "built\n");
+    }
+
+    #[test]
+    fn test_for_worker_gives_every_worker_its_uid() {
+        let mut base = sandbox(true);
+        base.uid_base = 20000;
+
+        let expected = |uid: u32| is_root().then_some(uid);
+        assert_eq!(base.for_worker(0).build_uid, expected(20000));
+        assert_eq!(base.for_worker(3).build_uid, expected(20003));
+
+        base.uid_base = 0;
+        assert_eq!(base.for_worker(3).build_uid, None);
+    }
+
+    #[test]
+    fn test_wrap_limits_processes_of_the_build_uid() {
+        if !is_root() {
+            eprintln!("not running as root, skipping");
+            return;
+        }
+
+        let mut limited = sandbox(false);
+        limited.max_processes = 64;
+        limited.build_uid = Some(20001);
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "id -u; id -g; grep 'Max processes' /proc/self/limits"]);
+
+        let output = limited
+            .wrap(command, Path::new("/tmp"), &[])
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = stdout.lines().collect();
+
+        assert_eq!(lines[..2], ["20001", "20001"]);
+        assert_eq!(
+            lines[2].split_whitespace().collect::<Vec<&str>>(),
+            ["Max", "processes", "64", "64", "processes"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_without_build_uid_has_no_process_limit() {
+        let mut limited = sandbox(false);
+        limited.max_processes = 64;
+
+        let mut command = Command::new("sh");
+        command.args(["-c", "grep 'Max processes' /proc/self/limits"]);
+
+        let output = limited
+            .wrap(command, Path::new("/tmp"), &[])
+            .output()
+            .unwrap();
+
+        // The limit would count the server threads and the other builds of the same user
+        assert!(!String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .any(|limit| limit == "64"));
+    }
+
+    #[test]
+    fn test_give_workspace_does_not_follow_links() {
+        if !is_root() {
+            eprintln!("not running as root, skipping");
+            return;
+        }
+
+        let workspace = env::temp_dir().join("compiler_be_test_give_workspace");
+        let outside = env::temp_dir().join("compiler_be_test_give_workspace_outside");
+        std::fs::create_dir_all(workspace.join("targets").join("key")).unwrap();
+        std::fs::write(workspace.join("targets").join("key").join("lib.rlib"), "").unwrap();
+        std::fs::write(&outside, "").unwrap();
+        std::os::unix::fs::symlink(&outside, workspace.join("link")).unwrap();
+
+        let mut build = sandbox(true);
+        build.build_uid = Some(20002);
+
+        build.give_workspace(&workspace, false).unwrap();
+        let shallow_owner = owner(&workspace.join("targets").join("key"));
+
+        build.give_workspace(&workspace, true).unwrap();
+        let deep_owner = owner(&workspace.join("targets").join("key").join("lib.rlib"));
+        let outside_owner = owner(&outside);
+
+        std::fs::remove_dir_all(&workspace).expect("Error deleting workspace");
+        std::fs::remove_file(&outside).expect("Error deleting file");
+
+        assert_eq!(shallow_owner, 0);
+        assert_eq!(deep_owner, 20002);
+        assert_eq!(outside_owner, 0);
+    }
+
+    fn owner(path: &Path) -> u32 {
+        use std::os::unix::fs::MetadataExt;
+
+        std::fs::symlink_metadata(path).unwrap().uid()
+    }
+}
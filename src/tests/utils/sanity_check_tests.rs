@@ -1,12 +1,20 @@
 #[cfg(test)]
 mod sanity_check_test {
 
+    use crate::models::api_models::BuildMode;
     use crate::models::db_models::Contract;
 
     use super::super::*;
 
     const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
 
+    fn profile(openbrush_version: Option<&str>) -> ToolchainProfile {
+        ToolchainProfile {
+            openbrush_version: openbrush_version.map(String::from),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_sanity_check_on_code_size_greater_than_maximum_allowed_size() {
         // Test case when the code size is greater than the maximum allowed size
@@ -14,6 +22,10 @@ mod sanity_check_test {
             code: String::from_utf8(vec![b'1'; MAX_SIZE_ALLOWED + 1]).unwrap(),
             address: String::from(BOB),
             features: vec![String::from("psp22"), String::from("pausable")],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_error = Err(Custom(
@@ -23,7 +35,11 @@ mod sanity_check_test {
             ))),
         ));
 
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_error);
         assert_eq!(result.is_err(), true);
         let error = &result.err().unwrap();
@@ -36,6 +52,10 @@ mod sanity_check_test {
             code: String::from_utf8(vec![b'1'; MAX_SIZE_ALLOWED - 1]).unwrap(),
             address: String::from("0x00e329d1fb7166f9cdf6a9e6cb62b6e5dfdd67ea"),
             features: vec![String::from("psp22"), String::from("pausable")],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_error = Err(Custom(
@@ -45,7 +65,11 @@ mod sanity_check_test {
             ))),
         ));
 
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_error);
         assert_eq!(result.is_err(), true);
         let error = &result.err().unwrap();
@@ -58,6 +82,10 @@ mod sanity_check_test {
             code: String::from_utf8(vec![b'1'; MAX_SIZE_ALLOWED - 1]).unwrap(),
             address: String::from(BOB),
             features: vec![],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_error = Err(Custom(
@@ -67,7 +95,11 @@ mod sanity_check_test {
             ))),
         ));
 
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_error);
         assert_eq!(result.is_err(), true);
         let error = &result.err().unwrap();
@@ -84,15 +116,25 @@ mod sanity_check_test {
                 String::from("pausable"),
                 String::from("recoverable"),
             ],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_error = Err(Custom(
             Status::InternalServerError,
-            Json(ServerResponse::<Contract>::new_error(String::from(
-                "Feature not allowed",
-            ))),
+            Json(ServerResponse::<Contract>::from_error(
+                ServerError::with_violations(vec![String::from(
+                    "Feature not allowed: recoverable",
+                )]),
+            )),
         ));
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_error);
         assert_eq!(result.is_err(), true);
         let error = &result.err().unwrap();
@@ -109,15 +151,25 @@ mod sanity_check_test {
                 String::from("pausable"),
                 String::from("psp34"),
             ],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_error = Err(Custom(
             Status::InternalServerError,
-            Json(ServerResponse::<Contract>::new_error(String::from(
-                "Feature contains ambiguous contract standard",
-            ))),
+            Json(ServerResponse::<Contract>::from_error(
+                ServerError::with_violations(vec![String::from(
+                    "Feature contains ambiguous contract standard",
+                )]),
+            )),
         ));
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_error);
         assert_eq!(result.is_err(), true);
         let error = &result.err().unwrap();
@@ -130,15 +182,25 @@ mod sanity_check_test {
             code: String::from_utf8(vec![b'1'; MAX_SIZE_ALLOWED - 1]).unwrap(),
             address: String::from(BOB),
             features: vec![String::from("pausable")],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_error = Err(Custom(
             Status::InternalServerError,
-            Json(ServerResponse::<Contract>::new_error(String::from(
-                "Features must contain at least one contract standard",
-            ))),
+            Json(ServerResponse::<Contract>::from_error(
+                ServerError::with_violations(vec![String::from(
+                    "Features must contain at least one contract standard",
+                )]),
+            )),
         ));
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_error);
         assert_eq!(result.is_err(), true);
         let error = &result.err().unwrap();
@@ -152,12 +214,160 @@ mod sanity_check_test {
             code: String::from_utf8(vec![b'1'; MAX_SIZE_ALLOWED - 1]).unwrap(),
             address: String::from(BOB),
             features: vec![String::from("psp22"), String::from("pausable")],
+            toolchain: None,
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
         };
 
         let expected_result = Ok(());
 
-        let result = sanity_check_wizard_message(&Json(wizard_message));
+        let result = sanity_check_wizard_message::<Contract>(
+            &Json(wizard_message),
+            &profile(Some("3.0.0")),
+            &FeatureRules::from_catalog(),
+        );
         assert_eq!(result, expected_result);
         assert_eq!(result.is_err(), false);
     }
+
+    #[test]
+    fn test_sanity_check_skips_features_without_openbrush() {
+        let wizard_message = WizardMessage {
+            code: String::from_utf8(vec![b'1'; MAX_SIZE_ALLOWED - 1]).unwrap(),
+            address: String::from(BOB),
+            features: vec![],
+            toolchain: Some(String::from("ink5")),
+            build_options: Default::default(),
+            lint: false,
+            priority: None,
+        };
+
+        let result = sanity_check_wizard_message::<Contract>(
+            &Json(wizard_message),
+            &profile(None),
+            &FeatureRules::from_catalog(),
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_sanity_check_on_wrong_build_options() {
+        let wrong_passes = BuildOptions {
+            optimization_passes: Some(String::from("9")),
+            ..Default::default()
+        };
+        let wrong_feature = BuildOptions {
+            features: vec![String::from("--manifest-path=/etc")],
+            ..Default::default()
+        };
+        let valid = BuildOptions {
+            mode: BuildMode::Debug,
+            optimization_passes: Some(String::from("z")),
+            keep_debug_symbols: true,
+            features: vec![String::from("ink-as-dependency")],
+        };
+
+        assert_eq!(
+            check_build_options(&wrong_passes),
+            Err(String::from("Optimization passes not allowed."))
+        );
+        assert_eq!(
+            check_build_options(&wrong_feature),
+            Err(String::from("Build feature not allowed."))
+        );
+        assert_eq!(check_build_options(&valid), Ok(()));
+    }
+
+    #[test]
+    fn test_check_priority_reserves_high_priority_for_admins() {
+        let expected_error = Err(Custom(
+            Status::Forbidden,
+            Json(ServerResponse::<Contract>::new_error(String::from(
+                "High priority is reserved for admins.",
+            ))),
+        ));
+
+        assert_eq!(
+            check_priority::<Contract>(&Some(JobPriority::High), false),
+            expected_error
+        );
+        assert_eq!(
+            check_priority::<Contract>(&Some(JobPriority::High), true),
+            Ok(())
+        );
+        assert_eq!(
+            check_priority::<Contract>(&Some(JobPriority::Low), false),
+            Ok(())
+        );
+        assert_eq!(check_priority::<Contract>(&None, false), Ok(()));
+    }
+
+    #[test]
+    fn test_check_features_on_extension_of_another_standard() {
+        let features = vec![String::from("psp34"), String::from("capped")];
+
+        let expected_error = Err(Custom(
+            Status::InternalServerError,
+            Json(ServerResponse::<Contract>::from_error(
+                ServerError::with_violations(vec![String::from("Feature capped requires psp22")]),
+            )),
+        ));
+        assert_eq!(
+            check_features::<Contract>(&features, &FeatureRules::from_catalog()),
+            expected_error
+        );
+
+        let features = vec![
+            String::from("psp34"),
+            String::from("enumerable"),
+            String::from("ownable"),
+        ];
+        assert_eq!(
+            check_features::<Contract>(&features, &FeatureRules::from_catalog()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_check_features_on_conflicting_features() {
+        let features = vec![
+            String::from("psp22"),
+            String::from("mintable"),
+            String::from("wrapper"),
+        ];
+
+        let expected_error = Err(Custom(
+            Status::InternalServerError,
+            Json(ServerResponse::<Contract>::from_error(
+                ServerError::with_violations(vec![String::from(
+                    "Feature mintable conflicts with wrapper",
+                )]),
+            )),
+        ));
+        assert_eq!(
+            check_features::<Contract>(&features, &FeatureRules::from_catalog()),
+            expected_error
+        );
+    }
+
+    #[test]
+    fn test_feature_catalog_is_consistent() {
+        for feature in FEATURES.iter() {
+            assert_eq!(
+                FEATURES.iter().filter(|f| f.name == feature.name).count(),
+                1
+            );
+            for standard in feature.standards {
+                assert!(CONTRACTS.contains(standard));
+            }
+            // Conflicts go both ways
+            for conflict in feature.conflicts {
+                assert!(find_feature(conflict)
+                    .unwrap()
+                    .conflicts
+                    .contains(&feature.name));
+            }
+        }
+    }
 }
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod test_report_test {
+    use super::super::*;
+
+    const CARGO_TEST_OUTPUT: &str = r#"{"reason":"compiler-artifact","target":{"name":"compiled_contract"}}
+{"reason":"build-finished","success":true}
+
+running 3 tests
+test my_psp22::tests::total_supply_works ... ok
+test my_psp22::tests::transfer_works ... FAILED
+test my_psp22::tests::slow_test ... ignored
+
+successes:
+
+---- my_psp22::tests::total_supply_works stdout ----
+total supply: 1000
+
+successes:
+    my_psp22::tests::total_supply_works
+
+failures:
+
+---- my_psp22::tests::transfer_works stdout ----
+thread 'my_psp22::tests::transfer_works' panicked at 'assertion failed: `(left == right)`
+  left: `900`,
+ right: `1000`', lib.rs:54:13
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    my_psp22::tests::transfer_works
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s
+"#;
+
+    #[test]
+    fn test_parse_test_output_reports_every_test() {
+        let report = parse_test_output(CARGO_TEST_OUTPUT);
+
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.ignored, 1);
+        assert_eq!(
+            report
+                .tests
+                .iter()
+                .map(|test| (test.name.as_str(), test.outcome.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("my_psp22::tests::total_supply_works", TestOutcome::Passed),
+                ("my_psp22::tests::transfer_works", TestOutcome::Failed),
+                ("my_psp22::tests::slow_test", TestOutcome::Ignored),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_test_output_keeps_captured_output() {
+        let report = parse_test_output(CARGO_TEST_OUTPUT);
+
+        assert_eq!(report.tests[0].output, "total supply: 1000");
+        assert!(report.tests[1]
+            .output
+            .starts_with("thread 'my_psp22::tests::transfer_works' panicked"));
+        assert!(report.tests[1].output.ends_with("to display a backtrace"));
+        assert_eq!(report.tests[2].output, "");
+    }
+
+    #[test]
+    fn test_parse_test_output_without_tests() {
+        let report = parse_test_output("\nrunning 0 tests\n\ntest result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s\n");
+
+        assert_eq!(report, TestReport::new(vec![]));
+    }
+}
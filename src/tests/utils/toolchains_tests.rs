@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod toolchains_test {
+    use super::super::*;
+
+    fn profile(name: &str, default: bool) -> ToolchainProfile {
+        ToolchainProfile {
+            name: name.to_string(),
+            rust_toolchain: String::from("nightly-2023-02-07"),
+            default,
+            template_dir: String::from("compilation_target"),
+            cargo: Some(String::from("cargo")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_without_name_returns_default_profile() {
+        let toolchains = Toolchains::new(vec![profile("ink5", false), profile("ink4", true)]);
+
+        assert_eq!(toolchains.get(None).unwrap().name, "ink4");
+    }
+
+    #[test]
+    fn test_default_profile_falls_back_to_first_one() {
+        let toolchains = Toolchains::new(vec![profile("ink5", false), profile("ink4", false)]);
+
+        assert_eq!(toolchains.default_profile().unwrap().name, "ink5");
+    }
+
+    #[test]
+    fn test_get_without_installed_profiles() {
+        let toolchains = Toolchains::new(vec![]);
+
+        assert_eq!(
+            toolchains.get(None),
+            Err(String::from("No toolchain installed"))
+        );
+    }
+
+    #[test]
+    fn test_get_by_name() {
+        let toolchains = Toolchains::new(vec![profile("ink5", false), profile("ink4", true)]);
+
+        assert_eq!(toolchains.get(Some("ink5")).unwrap().name, "ink5");
+    }
+
+    #[test]
+    fn test_get_not_installed_profile() {
+        let toolchains = Toolchains::new(vec![profile("ink4", true)]);
+
+        assert_eq!(
+            toolchains.get(Some("ink3")),
+            Err(String::from("Toolchain not available: ink3"))
+        );
+    }
+
+    #[test]
+    fn test_cargo_command_uses_profile_toolchain() {
+        let command = profile("ink4", true).cargo_command();
+
+        assert_eq!(command.get_program(), "cargo");
+        assert!(command
+            .get_envs()
+            .any(|(key, value)| key == "RUSTUP_TOOLCHAIN"
+                && value == Some(std::ffi::OsStr::new("nightly-2023-02-07"))));
+    }
+
+    #[test]
+    fn test_load_profiles_from_config() {
+        let profiles = Toolchains::load_profiles(DEFAULT_TOOLCHAINS_CONFIG).unwrap();
+
+        assert!(!profiles.is_empty());
+        assert_eq!(profiles.iter().filter(|profile| profile.default).count(), 1);
+        for profile in profiles {
+            assert!(std::path::Path::new(&profile.template_dir).is_dir());
+        }
+    }
+
+    #[test]
+    fn test_render_manifest_uses_profile_template() {
+        let manifest = profile("ink4", true)
+            .render_manifest(&[String::from("psp22")])
+            .unwrap();
+
+        assert!(manifest.contains("features = [\"psp22\"]"));
+    }
+
+    #[test]
+    fn test_check_installed_requires_cargo_contract_in_bin_path() {
+        let mut ink5 = profile("ink5", false);
+        ink5.bin_path = Some(String::from("/nonexistent/cargo-contract-4/bin"));
+
+        assert_eq!(
+            ink5.check_installed(),
+            Err(String::from(
+                "cargo-contract not found in /nonexistent/cargo-contract-4/bin"
+            ))
+        );
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod vendor_test {
+    use super::super::*;
+    use std::env;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "compiled_contract"
+version = "1.0.0"
+dependencies = ["ink"]
+
+[[package]]
+name = "ink"
+version = "4.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "openbrush"
+version = "3.0.0"
+source = "git+https://github.com/727-Ventures/openbrush-contracts?tag=3.0.0#1a2b3c"
+
+[[package]]
+name = "scale-info"
+version = "2.3.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    const VENDOR_CONFIG_OUTPUT: &str = r#"
+[source.crates-io]
+replace-with = "vendored-sources"
+
+[source."git+https://github.com/727-Ventures/openbrush-contracts?tag=3.0.0"]
+git = "https://github.com/727-Ventures/openbrush-contracts"
+tag = "3.0.0"
+replace-with = "vendored-sources"
+
+[source.vendored-sources]
+directory = "vendor/ink4-openbrush3"
+"#;
+
+    #[test]
+    fn test_missing_crates_reports_crates_not_vendored() {
+        let vendor_dir = env::temp_dir().join("compiler_be_test_vendor");
+        create_dir_all(vendor_dir.join("ink-4.0.0")).unwrap();
+        create_dir_all(vendor_dir.join("openbrush")).unwrap();
+        write(
+            vendor_dir.join("openbrush").join("Cargo.toml"),
+            "[package]\nname = \"openbrush\"\nversion = \"3.0.0\"\n",
+        )
+        .unwrap();
+
+        let missing = missing_crates(LOCKFILE, &vendor_dir).unwrap();
+
+        remove_dir_all(&vendor_dir).unwrap();
+        assert_eq!(missing, vec![String::from("scale-info 2.3.1")]);
+    }
+
+    #[test]
+    fn test_missing_crates_checks_vendored_version() {
+        let vendor_dir = env::temp_dir().join("compiler_be_test_vendor_version");
+        create_dir_all(vendor_dir.join("openbrush")).unwrap();
+        write(
+            vendor_dir.join("openbrush").join("Cargo.toml"),
+            "[package]\nname = \"openbrush\"\nversion = \"2.3.0\"\n",
+        )
+        .unwrap();
+
+        let missing = missing_crates(LOCKFILE, &vendor_dir).unwrap();
+
+        remove_dir_all(&vendor_dir).unwrap();
+        assert!(missing.contains(&String::from("openbrush 3.0.0")));
+        assert_eq!(missing.len(), 3);
+    }
+
+    #[test]
+    fn test_render_cargo_config_points_to_vendor_dir() {
+        let config =
+            render_cargo_config(VENDOR_CONFIG_OUTPUT, Path::new("/srv/vendor/ink4")).unwrap();
+        let config: Table = toml::from_str(&config).unwrap();
+
+        assert_eq!(
+            config["source"]["vendored-sources"]["directory"].as_str(),
+            Some("/srv/vendor/ink4")
+        );
+        assert_eq!(
+            config["source"]["crates-io"]["replace-with"].as_str(),
+            Some("vendored-sources")
+        );
+        assert_eq!(config["net"]["offline"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_vendor_script_covers_every_openbrush_feature() {
+        let script = std::fs::read_to_string("scripts/vendor.sh").unwrap();
+        let features_line = script
+            .lines()
+            .find(|line| line.starts_with("features="))
+            .unwrap();
+
+        for feature in crate::utils::sanity_check::FEATURES.iter() {
+            if let Some(openbrush_feature) = feature.openbrush_feature {
+                assert!(
+                    features_line.contains(&format!("\"{}\"", openbrush_feature)),
+                    "{} missing from scripts/vendor.sh",
+                    openbrush_feature
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use sha2::{Digest, Sha256};
+
+use crate::models::api_models::{BuildMode, BuildOptions};
+
+use super::toolchains::ToolchainProfile;
+
+// Canonical key of a build, two requests with the same key always produce the same artifact
+// It covers the source hash, the rendered manifest, the toolchain profile, the cargo-contract version,
+// the build options and whether the contract is linted, as the lints are stored with the contract
+pub fn build_key(
+    code_id: &str,
+    manifest: &str,
+    profile: &ToolchainProfile,
+    options: &BuildOptions,
+    lint: bool,
+) -> String {
+    let options = options_key(options, lint);
+    let parts = [
+        code_id,
+        manifest,
+        &profile.name,
+        &profile.rust_toolchain,
+        profile
+            .cargo_contract_version
+            .as_deref()
+            .unwrap_or_default(),
+        &options,
+    ];
+
+    let mut hasher = Sha256::new();
+    for part in parts {
+        // Parts are length prefixed so moving text from one part to the next changes the key
+        hasher.update(part.len().to_le_bytes());
+        hasher.update(part);
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+// Features are sorted so the order they were requested in does not change the key
+fn options_key(options: &BuildOptions, lint: bool) -> String {
+    let mode = match options.mode {
+        BuildMode::Debug => "debug",
+        BuildMode::Release => "release",
+    };
+    let mut features: Vec<&str> = options.features.iter().map(String::as_str).collect();
+    features.sort_unstable();
+    features.dedup();
+
+    format!(
+        "{};{};{};{};{}",
+        mode,
+        options.optimization_passes.as_deref().unwrap_or_default(),
+        options.keep_debug_symbols,
+        features.join(","),
+        lint
+    )
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/build_key_tests.rs"]
+mod build_key_tests;
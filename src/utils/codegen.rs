@@ -0,0 +1,307 @@
+use crate::models::api_models::GenerateMessage;
+use log::error;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+
+use super::sanity_check::CONTRACTS;
+
+// Contract rendered for the openbrush toolchains, same layout as compilation_target/template-lib.rs
+// Every placeholder is replaced by the lines of the standard and extensions requested
+pub const CONTRACT_TEMPLATE: &str = r#"#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+#[openbrush::contract]
+pub mod my_{{standard}} {
+    // imports from openbrush
+{{imports}}
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct Contract {
+{{storage_fields}}
+    }
+
+    // Section contains default implementation without any modifications
+{{impls}}
+
+    impl Contract {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            let mut _instance = Self::default();
+{{constructor}}
+            _instance
+        }{{messages}}
+    }
+}
+"#;
+
+// Extensions the templates know how to render, the other features need hand written code
+pub const GENERATED_EXTENSIONS: [&str; 3] = ["pausable", "ownable", "access-control"];
+
+// Decimals of a psp22 token when the request doesn't set them
+pub const DEFAULT_DECIMALS: u8 = 18;
+
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 12;
+
+// Checks the parameters that end up in the generated source
+// Names and symbols are restricted to plain characters, so they can't break out of their string literals
+pub fn check_generate_message(message: &GenerateMessage) -> Result<(), String> {
+    if !CONTRACTS.contains(&message.standard.as_str()) {
+        error!(target: "compiler", "Contract standard not allowed: {:?}", message.standard);
+        return Err("Contract standard not allowed.".to_string());
+    }
+
+    for extension in &message.extensions {
+        if !GENERATED_EXTENSIONS.contains(&extension.as_str()) {
+            error!(target: "compiler", "Extension not allowed: {:?}", extension);
+            return Err("Extension not allowed.".to_string());
+        }
+    }
+
+    let valid_name = !message.name.trim().is_empty()
+        && message.name.len() <= MAX_NAME_LEN
+        && message
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_');
+    if !valid_name {
+        error!(target: "compiler", "Contract name not valid: {:?}", message.name);
+        return Err("Contract name not valid.".to_string());
+    }
+
+    if let Some(symbol) = &message.symbol {
+        let valid_symbol = !symbol.is_empty()
+            && symbol.len() <= MAX_SYMBOL_LEN
+            && symbol.chars().all(|c| c.is_ascii_alphanumeric());
+        if !valid_symbol {
+            error!(target: "compiler", "Contract symbol not valid: {:?}", symbol);
+            return Err("Contract symbol not valid.".to_string());
+        }
+    }
+
+    if let Some(initial_supply) = &message.initial_supply {
+        if initial_supply.parse::<u128>().is_err() {
+            error!(target: "compiler", "Initial supply not valid: {:?}", initial_supply);
+            return Err("Initial supply not valid.".to_string());
+        }
+    }
+
+    if let Some(owner) = &message.owner {
+        if AccountId32::from_ss58check(owner).is_err() {
+            error!(target: "compiler", "Owner is not valid: {:?}", owner);
+            return Err("Owner is not valid.".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+// Features of the generated contract, the standard followed by its extensions
+pub fn generated_features(message: &GenerateMessage) -> Vec<String> {
+    let mut features = vec![message.standard.clone()];
+    for extension in &message.extensions {
+        if !features.contains(extension) {
+            features.push(extension.clone());
+        }
+    }
+
+    features
+}
+
+// Renders the source of the contract, the message must have passed check_generate_message
+pub fn render_contract(message: &GenerateMessage) -> String {
+    let features = generated_features(message);
+    let has = |feature: &str| features.iter().any(|f| f == feature);
+
+    let mut imports = Vec::new();
+    let mut storage_fields = Vec::new();
+    let mut impls = Vec::new();
+    let mut constructor = Vec::new();
+    let mut messages = Vec::new();
+
+    let owner = owner_expression(message.owner.as_deref());
+    let standard = message.standard.as_str();
+    let error = format!("{}Error", standard.to_uppercase());
+
+    // Standard and its metadata
+    match standard {
+        "psp22" => {
+            imports.push("use openbrush::contracts::psp22::extensions::metadata::*;");
+            imports.push("use openbrush::traits::String;");
+            storage_fields.push(storage_field("psp22", "psp22::Data"));
+            storage_fields.push(storage_field("metadata", "metadata::Data"));
+            impls.push(String::from("    impl PSP22 for Contract {}"));
+            impls.push(String::from("    impl PSP22Metadata for Contract {}"));
+
+            constructor.push(format!(
+                "_instance.metadata.name = Some(String::from(\"{}\"));",
+                message.name
+            ));
+            if let Some(symbol) = &message.symbol {
+                constructor.push(format!(
+                    "_instance.metadata.symbol = Some(String::from(\"{}\"));",
+                    symbol
+                ));
+            }
+            constructor.push(format!(
+                "_instance.metadata.decimals = {};",
+                message.decimals.unwrap_or(DEFAULT_DECIMALS)
+            ));
+
+            let initial_supply = message
+                .initial_supply
+                .as_deref()
+                .and_then(|supply| supply.parse::<u128>().ok())
+                .unwrap_or_default();
+            if initial_supply > 0 {
+                constructor.push(format!(
+                    "_instance\n                ._mint_to({}, {})\n                .expect(\"Should mint\");",
+                    owner, initial_supply
+                ));
+            }
+        }
+        "psp34" => {
+            imports.push("use openbrush::contracts::psp34::extensions::metadata::*;");
+            imports.push("use openbrush::traits::String;");
+            storage_fields.push(storage_field("psp34", "psp34::Data"));
+            storage_fields.push(storage_field("metadata", "metadata::Data"));
+            impls.push(String::from("    impl PSP34 for Contract {}"));
+            impls.push(String::from("    impl PSP34Metadata for Contract {}"));
+
+            constructor.push(String::from(
+                "let collection_id = _instance.collection_id();",
+            ));
+            constructor.push(set_attribute("name", &message.name));
+            if let Some(symbol) = &message.symbol {
+                constructor.push(set_attribute("symbol", symbol));
+            }
+        }
+        _ => {
+            imports.push("use openbrush::contracts::psp37::*;");
+            storage_fields.push(storage_field("psp37", "psp37::Data"));
+            impls.push(String::from("    impl PSP37 for Contract {}"));
+        }
+    }
+
+    // Extensions
+    if has("ownable") {
+        imports.push("use openbrush::contracts::ownable::*;");
+        storage_fields.push(storage_field("ownable", "ownable::Data"));
+        impls.push(String::from("    impl Ownable for Contract {}"));
+        constructor.push(format!("_instance._init_with_owner({});", owner));
+    }
+
+    if has("access-control") {
+        imports.push("use openbrush::contracts::access_control::*;");
+        storage_fields.push(storage_field("access", "access_control::Data"));
+        impls.push(String::from("    impl AccessControl for Contract {}"));
+        constructor.push(format!("_instance._init_with_admin({});", owner));
+    }
+
+    if has("pausable") {
+        imports.push("use openbrush::contracts::pausable::*;");
+        storage_fields.push(storage_field("pausable", "pausable::Data"));
+        impls.push(String::from("    impl Pausable for Contract {}"));
+        impls.push(transfer_hook(standard, &error));
+
+        // Pausing is restricted to the owner or the admin when the contract has one
+        let guard = if has("ownable") {
+            Some("only_owner")
+        } else if has("access-control") {
+            Some("only_role(DEFAULT_ADMIN_ROLE)")
+        } else {
+            None
+        };
+        messages.push(change_state_message(guard, &error));
+    }
+
+    CONTRACT_TEMPLATE
+        .replace("{{standard}}", standard)
+        .replace("{{imports}}", &indent_lines(&imports, 4))
+        .replace("{{storage_fields}}", &storage_fields.join("\n"))
+        .replace("{{impls}}", &impls.join("\n"))
+        .replace("{{constructor}}", &indent_lines(&constructor, 12))
+        .replace("{{messages}}", &messages.join(""))
+}
+
+// Expression of the account that owns the contract, the deployer when no owner is set
+fn owner_expression(owner: Option<&str>) -> String {
+    match owner.and_then(|owner| AccountId32::from_ss58check(owner).ok()) {
+        Some(account) => {
+            let bytes: &[u8; 32] = account.as_ref();
+            let bytes = bytes
+                .iter()
+                .map(|byte| format!("{:#04x}", byte))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("AccountId::from([{}])", bytes)
+        }
+        None => String::from("_instance.env().caller()"),
+    }
+}
+
+fn storage_field(name: &str, data: &str) -> String {
+    format!("        #[storage_field]\n        {}: {},", name, data)
+}
+
+fn set_attribute(key: &str, value: &str) -> String {
+    format!(
+        "_instance._set_attribute(\n                collection_id.clone(),\n                String::from(\"{}\"),\n                String::from(\"{}\"),\n            );",
+        key, value
+    )
+}
+
+// Transfers of a pausable contract fail while it is paused
+fn transfer_hook(standard: &str, error: &str) -> String {
+    let transferred = match standard {
+        "psp22" => "_amount: &Balance",
+        "psp34" => "_id: &Id",
+        _ => "_ids_amounts: &[(Id, Balance)]",
+    };
+
+    format!(
+        r#"
+    impl {standard}::Transfer for Contract {{
+        #[openbrush::modifiers(when_not_paused)]
+        fn _before_token_transfer(
+            &mut self,
+            _from: Option<&AccountId>,
+            _to: Option<&AccountId>,
+            {transferred},
+        ) -> Result<(), {error}> {{
+            Ok(())
+        }}
+    }}"#
+    )
+}
+
+fn change_state_message(guard: Option<&str>, error: &str) -> String {
+    let modifier = guard
+        .map(|guard| format!("\n        #[openbrush::modifiers({})]", guard))
+        .unwrap_or_default();
+
+    format!(
+        r#"
+        #[ink(message)]{modifier}
+        pub fn change_state(&mut self) -> Result<(), {error}> {{
+            if self.paused() {{
+                self._unpause()
+            }} else {{
+                self._pause()
+            }}
+        }}"#
+    )
+}
+
+fn indent_lines(lines: &[impl AsRef<str>], indent: usize) -> String {
+    lines
+        .iter()
+        .map(|line| format!("{}{}", " ".repeat(indent), line.as_ref()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/codegen_tests.rs"]
+mod codegen_tests;
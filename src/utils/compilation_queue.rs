@@ -1,19 +1,150 @@
-use super::super::models::api_models::WizardMessage;
-use crate::models::db_models::Contract;
-use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
-use log::{error};
+use super::super::models::api_models::{
+    Diagnostic, QueueSummary, ServerError, TestReport, WizardMessage,
+};
+use crate::models::db_models::{
+    BuildMetrics, CompilationJob, Contract, JobKind, JobPriority, JobRecord, JobStatus,
+};
+use crate::repository::mongodb_repo::MongoRepo;
+use log::{error, info, warn};
+
+use super::fair_queue::FairQueue;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// Finished jobs are kept around this long so clients can still poll their result
+pub const JOB_RETENTION: Duration = Duration::from_secs(3600);
+
+// Times a worker can take a job, a job still compiling after a restart is only retried below it
+pub const MAX_JOB_ATTEMPTS: u32 = 2;
+
+// Jobs waiting in the queue when MAX_QUEUE_DEPTH is not set
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 100;
+
+// Unfinished jobs an address can have when MAX_REQUESTS_PER_ADDRESS is not set
+pub const DEFAULT_MAX_REQUESTS_PER_ADDRESS: usize = 5;
+
+// Seconds a request blocks waiting for its job when CLIENT_WAIT_TIMEOUT is not set, 0 waits until it finishes
+pub const DEFAULT_CLIENT_WAIT_TIMEOUT: usize = 0;
+
+// Amount of recent jobs the wait estimates and the queue summary are based on
+const RECENT_JOBS: usize = 20;
+
+// Build duration assumed until the first build finishes
+const DEFAULT_BUILD_DURATION: Duration = Duration::from_secs(60);
+
+// Limits on the work the queue accepts, 0 disables a limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueLimits {
+    pub max_depth: usize,
+    pub max_per_address: usize,
+    // Seconds a request blocks waiting for its job before giving up on it
+    pub client_wait_secs: usize,
+}
+
+impl QueueLimits {
+    // Reads the limits from the MAX_QUEUE_DEPTH, MAX_REQUESTS_PER_ADDRESS and CLIENT_WAIT_TIMEOUT env variables
+    pub fn init() -> Self {
+        QueueLimits {
+            max_depth: limit_from_env("MAX_QUEUE_DEPTH", DEFAULT_MAX_QUEUE_DEPTH),
+            max_per_address: limit_from_env(
+                "MAX_REQUESTS_PER_ADDRESS",
+                DEFAULT_MAX_REQUESTS_PER_ADDRESS,
+            ),
+            client_wait_secs: limit_from_env("CLIENT_WAIT_TIMEOUT", DEFAULT_CLIENT_WAIT_TIMEOUT),
+        }
+    }
+
+    // Limits that accept any amount of work
+    pub fn unlimited() -> Self {
+        QueueLimits {
+            max_depth: 0,
+            max_per_address: 0,
+            client_wait_secs: 0,
+        }
+    }
+
+    // Time a request waits for its job, None when it waits until the job finishes
+    fn client_wait(&self) -> Option<Duration> {
+        match self.client_wait_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs as u64)),
+        }
+    }
+}
+
+fn limit_from_env(name: &str, default: usize) -> usize {
+    match env::var(name) {
+        Ok(v) => v.parse::<usize>().unwrap_or_else(|_| {
+            error!(target: "compiler", "Invalid {} value: {}", name, v);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+// Reasons a request is not queued, rejected requests carry the seconds to wait before retrying
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueError {
+    QueueFull { retry_after: u64 },
+    AddressLimit { retry_after: u64 },
+    Internal(String),
+}
+
+// Reasons a job could not be cancelled
+#[derive(Debug, Clone, PartialEq)]
+pub enum CancelError {
+    NotFound,
+    Finished,
+    // The release token is not one of a client polling the job
+    InvalidToken,
+    Internal(String),
+}
 
 // Compilation Request structure
 pub struct CompilationRequest {
     pub wizard_message: WizardMessage,
     pub code_id: String,
-    pub tx: mpsc::Sender<Result<Contract, String>>,
+    pub build_key: String,
+    pub kind: JobKind,
+    pub priority: JobPriority,
+    pub job_id: String,
+    pub queued_at: Instant,
+}
+
+// Job a request ended up in, attached is set when the request joined a job that was already
+// queued or compiling the same build key instead of creating a new one
+// Polled requests get the token their client releases the job with
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedJob {
+    pub job_id: String,
+    pub attached: bool,
+    pub release_token: Option<String>,
 }
 
 // Compilation Queue is a thread-safe queue that holds CompilationRequests
+// and keeps track of the job created for each one of them
 pub struct CompilationQueue {
-    pub queue: Arc<Mutex<Vec<CompilationRequest>>>,
+    pub queue: Arc<Mutex<FairQueue>>,
+    pub jobs: Arc<Mutex<HashMap<String, CompilationJob>>>,
+    pub job_finished: Arc<Condvar>,
+    // Wakes up the idle workers when a request is queued or the server shuts down
+    pub request_queued: Arc<Condvar>,
+    // Unfinished jobs are persisted in the db when set, so they survive restarts
+    pub db: Option<MongoRepo>,
+    pub limits: QueueLimits,
+    // Workers taking requests from the queue, used to estimate how long jobs wait
+    pub workers: usize,
+    // Durations in milliseconds of the last builds
+    pub recent_durations: Arc<Mutex<VecDeque<u64>>>,
+    // Milliseconds the last requests waited in the queue before a worker took them
+    pub recent_waits: Arc<Mutex<VecDeque<u64>>>,
+    // Cancel flags of the jobs being compiled, set to kill the cargo process of a cancelled job
+    pub running: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
 }
 
 // Compilation Queue implementation
@@ -21,39 +152,897 @@ impl CompilationQueue {
     // Create a new CompilationQueue
     pub fn new() -> CompilationQueue {
         CompilationQueue {
-            queue: Arc::new(Mutex::new(Vec::new())),
+            queue: Arc::new(Mutex::new(FairQueue::new())),
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            job_finished: Arc::new(Condvar::new()),
+            request_queued: Arc::new(Condvar::new()),
+            db: None,
+            limits: QueueLimits::unlimited(),
+            workers: 1,
+            recent_durations: Arc::new(Mutex::new(VecDeque::new())),
+            recent_waits: Arc::new(Mutex::new(VecDeque::new())),
+            running: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // Create a new CompilationQueue with limits on the work it accepts
+    pub fn with_limits(limits: QueueLimits, workers: usize) -> CompilationQueue {
+        CompilationQueue {
+            limits,
+            workers: workers.max(1),
+            ..Self::new()
         }
     }
 
-    // Add a CompilationRequest to the queue
-    pub fn add_request(&self, request: CompilationRequest) {
+    // Create a new CompilationQueue persisting its jobs in the db
+    pub fn with_db(db: MongoRepo, limits: QueueLimits, workers: usize) -> CompilationQueue {
+        CompilationQueue {
+            db: Some(db),
+            ..Self::with_limits(limits, workers)
+        }
+    }
+
+    // Add a CompilationRequest to the queue, returns the job tracking it
+    // When the same build key is already queued or compiling the request is attached to that job,
+    // so every caller gets the result of a single build
+    pub fn add_request(
+        &self,
+        wizard_message: WizardMessage,
+        code_id: String,
+        build_key: String,
+    ) -> Result<QueuedJob, QueueError> {
+        self.enqueue(wizard_message, code_id, build_key, JobKind::Build, false)
+    }
+
+    // Add a CompilationRequest whose client polls the job instead of waiting for it
+    // The client keeps the job alive until it is finished or it releases the job with its release token
+    pub fn add_polled_request(
+        &self,
+        wizard_message: WizardMessage,
+        code_id: String,
+        build_key: String,
+    ) -> Result<QueuedJob, QueueError> {
+        self.enqueue(wizard_message, code_id, build_key, JobKind::Build, true)
+    }
+
+    // Add a check-only request to the queue, checks are high priority so they are taken ahead of the builds
+    pub fn add_check_request(
+        &self,
+        wizard_message: WizardMessage,
+        code_id: String,
+        build_key: String,
+    ) -> Result<QueuedJob, QueueError> {
+        self.enqueue(wizard_message, code_id, build_key, JobKind::Check, false)
+    }
+
+    // Add a request running the unit tests of the contract to the queue
+    pub fn add_test_request(
+        &self,
+        wizard_message: WizardMessage,
+        code_id: String,
+        build_key: String,
+    ) -> Result<QueuedJob, QueueError> {
+        self.enqueue(wizard_message, code_id, build_key, JobKind::Test, false)
+    }
+
+    // Registers the job of a request and pushes the request to the queue
+    fn enqueue(
+        &self,
+        wizard_message: WizardMessage,
+        code_id: String,
+        build_key: String,
+        kind: JobKind,
+        polled: bool,
+    ) -> Result<QueuedJob, QueueError> {
+        // The job has to be registered before the request is visible to the compiler
+        let mut job = CompilationJob::new(&code_id, &build_key, kind);
+        job.address = wizard_message.address.clone();
+        if polled {
+            let token = release_token().map_err(|e| {
+                error!(target: "compiler", "Error creating release token: {:?}", e);
+                QueueError::Internal(String::from("Error queueing contract."))
+            })?;
+            job.pollers.push(token);
+        }
+        let queued_job = self.insert_or_attach_job(job)?;
+        if queued_job.attached {
+            return Ok(queued_job);
+        }
+
+        let priority = request_priority(&wizard_message, kind);
+        let request = CompilationRequest {
+            wizard_message,
+            code_id,
+            build_key,
+            kind,
+            priority,
+            job_id: queued_job.job_id.clone(),
+            queued_at: Instant::now(),
+        };
+
+        // The record is created before a worker can mark it as compiling
+        if let Some(db) = &self.db {
+            let record = JobRecord {
+                job_id: request.job_id.clone(),
+                code_id: request.code_id.clone(),
+                build_key: request.build_key.clone(),
+                kind,
+                status: JobStatus::Queued,
+                wizard_message: request.wizard_message.clone(),
+                attempts: 0,
+            };
+            if db.create_job(&record).is_err() {
+                error!(target: "compiler", "Error persisting job {}", &record.job_id);
+            }
+        }
+
+        if !self.push_request(request) {
+            self.finish_job(
+                &queued_job.job_id,
+                Err(ServerError::new(String::from("Error queueing contract."))),
+            );
+            return Err(QueueError::Internal(String::from(
+                "Error queueing contract.",
+            )));
+        }
+
+        Ok(queued_job)
+    }
+
+    // Pushes a request to the back of the queue, returns false if the queue can't be locked
+    fn push_request(&self, request: CompilationRequest) -> bool {
         let queue_res = self.queue.lock();
 
         if queue_res.is_err() {
             error!(target: "compiler", "Error locking queue");
-            return;
+            return false;
         }
 
-        let mut queue = queue_res.expect("This will never panic because we checked for errors before");
+        let mut queue =
+            queue_res.expect("This will never panic because we checked for errors before");
 
         queue.push(request);
+        self.request_queued.notify_one();
+
+        true
+    }
+
+    // Requeues the jobs persisted by a previous run, returns the ids of the requeued builds
+    pub fn recover_jobs(&self) -> Vec<String> {
+        let records = match &self.db {
+            Some(db) => match db.get_jobs() {
+                Ok(records) => records,
+                Err(_) => {
+                    error!(target: "compiler", "Error fetching persisted jobs");
+                    return vec![];
+                }
+            },
+            None => return vec![],
+        };
+        info!(target: "compiler", "Recovering {} persisted jobs", records.len());
+
+        self.restore_jobs(records)
+    }
+
+    // Queued jobs are requeued with their id, so clients can keep polling them
+    // Jobs left compiling are retried unless they already used all their attempts,
+    // which keeps a contract that crashes the server from crashing it again on every start
+    pub fn restore_jobs(&self, records: Vec<JobRecord>) -> Vec<String> {
+        let mut requeued_builds = Vec::new();
+
+        for record in records {
+            let mut job = CompilationJob::new(&record.code_id, &record.build_key, record.kind);
+            job.id = record.job_id.clone();
+            job.address = record.wizard_message.address.clone();
+
+            if record.status == JobStatus::Compiling && record.attempts >= MAX_JOB_ATTEMPTS {
+                warn!(target: "compiler", "Job {} failed after {} attempts", &record.job_id, record.attempts);
+                job.finish(Err(ServerError::new(String::from(
+                    "Compilation interrupted by a server restart.",
+                ))));
+                if self.insert_job(job).is_ok() {
+                    self.forget_job(&record.job_id);
+                }
+
+                continue;
+            }
+
+            if self.insert_job(job).is_err() {
+                continue;
+            }
+
+            let request = CompilationRequest {
+                priority: request_priority(&record.wizard_message, record.kind),
+                wizard_message: record.wizard_message,
+                code_id: record.code_id,
+                build_key: record.build_key,
+                kind: record.kind,
+                job_id: record.job_id,
+                queued_at: Instant::now(),
+            };
+            let job_id = request.job_id.clone();
+
+            if !self.push_request(request) {
+                continue;
+            }
+            info!(target: "compiler", "Job {} requeued", &job_id);
+
+            if record.kind == JobKind::Build {
+                requeued_builds.push(job_id);
+            }
+        }
+
+        requeued_builds
     }
 
-    // Take a CompilationRequest from the queue
+    // Registers a job that is already finished, used for contracts found in the db
+    pub fn add_finished_job(&self, contract: Contract) -> Result<String, String> {
+        let mut job = CompilationJob::new(&contract.code_id, &contract.build_key, JobKind::Build);
+        let job_id = job.id.clone();
+        job.finish(Ok(contract));
+
+        self.insert_job(job)?;
+
+        Ok(job_id)
+    }
+
+    // Take a CompilationRequest from the queue, following the priority classes and the turns of the addresses
+    // Requests of jobs cancelled while they were being taken are skipped
     pub fn take_request(&self) -> Option<CompilationRequest> {
+        let request = loop {
+            let request = self.pop_request()?;
+            if self.start_job(&request.job_id) {
+                break request;
+            }
+        };
+        push_sample(
+            &self.recent_waits,
+            request.queued_at.elapsed().as_millis() as u64,
+        );
+
+        if let Some(db) = &self.db {
+            if db.mark_job_compiling(&request.job_id).is_err() {
+                error!(target: "compiler", "Error updating persisted job {}", &request.job_id);
+            }
+        }
+
+        Some(request)
+    }
+
+    // Blocks until a request can be taken, returns None once the shutdown flag is set
+    // The flag is checked under the queue lock, so a shutdown is never missed by a worker about to wait
+    pub fn wait_for_request(&self, shutdown_flag: &AtomicBool) -> Option<CompilationRequest> {
+        loop {
+            if shutdown_flag.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            if let Some(request) = self.take_request() {
+                return Some(request);
+            }
+
+            let queue_res = self.queue.lock();
+
+            if queue_res.is_err() {
+                error!(target: "compiler", "Error locking queue");
+                return None;
+            }
+
+            let queue =
+                queue_res.expect("This will never panic because we checked for errors before");
+
+            if queue.len() == 0
+                && !shutdown_flag.load(Ordering::Relaxed)
+                && self.request_queued.wait(queue).is_err()
+            {
+                error!(target: "compiler", "Error waiting for requests");
+                return None;
+            }
+        }
+    }
+
+    // Wakes up every idle worker, used after setting the shutdown flag
+    pub fn wake_workers(&self) {
+        match self.queue.lock() {
+            Ok(_queue) => self.request_queued.notify_all(),
+            Err(_) => error!(target: "compiler", "Error locking queue"),
+        }
+    }
+
+    // Moves a queued job to compiling, false when it is no longer queued
+    fn start_job(&self, job_id: &str) -> bool {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return false;
+        }
+
+        let mut jobs =
+            jobs_res.expect("This will never panic because we checked for errors before");
+
+        match jobs.get_mut(job_id) {
+            Some(job) if job.status == JobStatus::Queued => {
+                job.status = JobStatus::Compiling;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Registers the cancel flag of the worker compiling a job, false when the job was already cancelled
+    // Registering under the jobs lock makes sure a cancel either sees the flag or the worker sees the cancel
+    pub fn watch_running_job(&self, job_id: &str, cancelled: &Arc<AtomicBool>) -> bool {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return false;
+        }
+
+        let jobs = jobs_res.expect("This will never panic because we checked for errors before");
+
+        match jobs.get(job_id) {
+            Some(job) if !job.is_finished() => {
+                cancelled.store(false, Ordering::Relaxed);
+                if let Ok(mut running) = self.running.lock() {
+                    running.insert(job_id.to_string(), cancelled.clone());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Cancels a job whoever else is waiting for it or polling it
+    pub fn cancel_job(&self, job_id: &str) -> Result<CompilationJob, CancelError> {
+        self.release_or_cancel_job(job_id, None)
+    }
+
+    // Drops the interest of the client polling a job with the release token, returning the job as it is left
+    // The job is only cancelled once no request waits for it and no other client polls it
+    pub fn release_job(&self, job_id: &str, token: &str) -> Result<CompilationJob, CancelError> {
+        self.release_or_cancel_job(job_id, Some(token))
+    }
+
+    // Without a release token the job is cancelled whoever else wants it
+    fn release_or_cancel_job(
+        &self,
+        job_id: &str,
+        token: Option<&str>,
+    ) -> Result<CompilationJob, CancelError> {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return Err(CancelError::Internal(String::from("Error cancelling job.")));
+        }
+
+        let mut jobs =
+            jobs_res.expect("This will never panic because we checked for errors before");
+
+        let job = match jobs.get_mut(job_id) {
+            Some(job) if job.is_finished() => return Err(CancelError::Finished),
+            Some(job) => job,
+            None => return Err(CancelError::NotFound),
+        };
+
+        if let Some(token) = token {
+            // Every token releases the job once, so a client can't release it for the others
+            match job.pollers.iter().position(|poller| poller == token) {
+                Some(poller) => job.pollers.swap_remove(poller),
+                None => return Err(CancelError::InvalidToken),
+            };
+            if !job.pollers.is_empty() || job.waiters > 0 {
+                info!(target: "compiler", "Job {} released, other requests still wait for it", job_id);
+                return Ok(job.clone());
+            }
+        }
+
+        Ok(self.cancel_locked(jobs, job_id))
+    }
+
+    // Cancels an unfinished job, a queued job is removed from the queue and a compiling one has its cargo process killed
+    // Everyone waiting for the job is woken up with the cancellation error
+    // The jobs lock is held until the job is cancelled, so nobody can attach to it in between
+    fn cancel_locked(
+        &self,
+        mut jobs: MutexGuard<HashMap<String, CompilationJob>>,
+        job_id: &str,
+    ) -> CompilationJob {
+        let job = jobs
+            .get_mut(job_id)
+            .expect("This will never panic because the caller checked the job under the same lock");
+        job.cancel();
+        let job = job.clone();
+
+        if let Ok(running) = self.running.lock() {
+            if let Some(cancelled) = running.get(job_id) {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        }
+        self.job_finished.notify_all();
+        // The jobs lock is released before taking the queue lock, the queue is always locked first
+        drop(jobs);
+
+        match self.queue.lock() {
+            Ok(mut queue) => {
+                queue.remove(job_id);
+            }
+            Err(_) => error!(target: "compiler", "Error locking queue"),
+        }
+        self.forget_job(job_id);
+        info!(target: "compiler", "Job {} cancelled", job_id);
+
+        job
+    }
+
+    // Removes the next request from the queue
+    fn pop_request(&self) -> Option<CompilationRequest> {
+        let queue_res = self.queue.lock();
+
+        if queue_res.is_err() {
+            error!(target: "compiler", "Error locking queue");
+            return None;
+        }
+
+        let mut queue =
+            queue_res.expect("This will never panic because we checked for errors before");
+
+        queue.pop()
+    }
+
+    // Fails every request still waiting in the queue, used when the compiler shuts down
+    // Their persisted records are kept, so the jobs are requeued on the next start
+    pub fn fail_queued_requests(&self, message: &str) {
         let queue_res = self.queue.lock();
 
         if queue_res.is_err() {
             error!(target: "compiler", "Error locking queue");
+            return;
+        }
+
+        let mut queue =
+            queue_res.expect("This will never panic because we checked for errors before");
+
+        for request in queue.drain() {
+            self.update_job(&request.job_id, |job| {
+                job.finish(Err(ServerError::new(message.to_string())))
+            });
+        }
+    }
+
+    // Get a snapshot of a job, queued jobs include their position and when they should start
+    pub fn get_job(&self, job_id: &str) -> Option<CompilationJob> {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
             return None;
         }
 
-        let mut queue = queue_res.expect("This will never panic because we checked for errors before");
-        
-        if queue.is_empty() {
+        let jobs = jobs_res.expect("This will never panic because we checked for errors before");
+
+        let mut job = jobs.get(job_id).cloned()?;
+        let active_builds = Self::active_builds(&jobs);
+        drop(jobs);
+
+        if job.status == JobStatus::Queued {
+            if let Some(ahead) = self.requests_ahead(job_id) {
+                job.queue_position = Some(ahead + 1);
+                job.estimated_start_secs = Some(self.estimate_start(ahead, active_builds));
+            }
+        }
+
+        Some(job)
+    }
+
+    // Amount of requests workers take before the given one, following the order of take_request
+    fn requests_ahead(&self, job_id: &str) -> Option<usize> {
+        let queue_res = self.queue.lock();
+
+        if queue_res.is_err() {
+            error!(target: "compiler", "Error locking queue");
+            return None;
+        }
+
+        let queue = queue_res.expect("This will never panic because we checked for errors before");
+
+        queue.requests_ahead(job_id)
+    }
+
+    // Seconds until a request with the given amount of requests ahead is taken by a worker
+    // It starts right away when there are idle workers left for it
+    fn estimate_start(&self, ahead: usize, active_builds: usize) -> u64 {
+        let idle_workers = self.workers.saturating_sub(active_builds);
+        if ahead < idle_workers {
+            return 0;
+        }
+
+        self.estimate_wait(ahead - idle_workers + 1)
+    }
+
+    // Jobs the workers are running
+    fn active_builds(jobs: &HashMap<String, CompilationJob>) -> usize {
+        jobs.values()
+            .filter(|job| job.status == JobStatus::Compiling)
+            .count()
+    }
+
+    // Summary of the queue for the GET /queue endpoint
+    pub fn summary(&self) -> QueueSummary {
+        let depth = match self.queue.lock() {
+            Ok(queue) => queue.len(),
+            Err(_) => {
+                error!(target: "compiler", "Error locking queue");
+                0
+            }
+        };
+        let active_builds = match self.jobs.lock() {
+            Ok(jobs) => Self::active_builds(&jobs),
+            Err(_) => {
+                error!(target: "compiler", "Error locking jobs");
+                0
+            }
+        };
+
+        QueueSummary {
+            depth,
+            active_builds,
+            workers: self.workers,
+            average_build_ms: average_sample(&self.recent_durations),
+            average_wait_ms: average_sample(&self.recent_waits),
+        }
+    }
+
+    // Stores the result of a job and wakes up everyone waiting for it
+    // Built contracts are saved in the db before anyone is woken up, whichever request created the job
+    pub fn finish_job(&self, job_id: &str, result: Result<Contract, ServerError>) {
+        if let (Some(db), Ok(contract)) = (&self.db, &result) {
+            store_contract(db, contract);
+        }
+        self.complete_job(job_id, |job| job.finish(result));
+    }
+
+    // Stores the diagnostics of a check job and wakes up everyone waiting for it
+    pub fn finish_check_job(&self, job_id: &str, result: Result<Vec<Diagnostic>, ServerError>) {
+        self.complete_job(job_id, |job| job.finish_check(result));
+    }
+
+    // Stores the report of a test job and wakes up everyone waiting for it
+    pub fn finish_test_job(&self, job_id: &str, result: Result<TestReport, ServerError>) {
+        self.complete_job(job_id, |job| job.finish_test(result));
+    }
+
+    // Stores the result of a job unless it was cancelled meanwhile, then drops its worker flag and record
+    fn complete_job(&self, job_id: &str, finish: impl FnOnce(&mut CompilationJob)) {
+        self.update_job(job_id, |job| {
+            if !job.is_finished() {
+                finish(job)
+            }
+        });
+
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(job_id);
+        }
+        self.forget_job(job_id);
+    }
+
+    // Deletes the persisted record of a finished job
+    fn forget_job(&self, job_id: &str) {
+        if let Some(db) = &self.db {
+            if db.delete_job(job_id).is_err() {
+                error!(target: "compiler", "Error deleting persisted job {}", job_id);
+            }
+        }
+    }
+
+    // Blocks until the job is finished or the client wait timeout, if any, is reached
+    // A request can't tell its client disconnected while it blocks, the timeout is the only way it gives up
+    pub fn wait_for_client(&self, job_id: &str) -> Result<CompilationJob, ServerError> {
+        self.wait_with_timeout(job_id, self.limits.client_wait())
+    }
+
+    // Blocks until the job is finished, counting the caller as a waiter of the job
+    // The job is cancelled when the last waiter gives up on it and no client polls it, nobody would get its result
+    fn wait_with_timeout(
+        &self,
+        job_id: &str,
+        timeout: Option<Duration>,
+    ) -> Result<CompilationJob, ServerError> {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return Err(ServerError::new(String::from("Error compiling contract")));
+        }
+
+        let mut jobs =
+            jobs_res.expect("This will never panic because we checked for errors before");
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut waiting = false;
+
+        let result = loop {
+            match jobs.get_mut(job_id) {
+                Some(job) if job.is_finished() => break Ok(job.clone()),
+                Some(job) if !waiting => {
+                    job.waiters += 1;
+                    waiting = true;
+                }
+                Some(_) => (),
+                None => {
+                    error!(target: "compiler", "Job {} not found", job_id);
+                    break Err(ServerError::new(String::from("Error compiling contract")));
+                }
+            }
+
+            let wait_res = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        warn!(target: "compiler", "Gave up waiting for job {}", job_id);
+                        break Err(ServerError::new(String::from(
+                            "Timed out waiting for the job.",
+                        )));
+                    }
+                    self.job_finished
+                        .wait_timeout(jobs, deadline - now)
+                        .map(|(jobs, _)| jobs)
+                        .map_err(|_| ())
+                }
+                None => self.job_finished.wait(jobs).map_err(|_| ()),
+            };
+
+            jobs = match wait_res {
+                Ok(jobs) => jobs,
+                Err(_) => {
+                    error!(target: "compiler", "Error waiting for job {}", job_id);
+                    return Err(ServerError::new(String::from("Error compiling contract")));
+                }
+            };
+        };
+
+        let abandoned = match jobs.get_mut(job_id) {
+            Some(job) if waiting => {
+                job.waiters -= 1;
+                job.waiters == 0 && job.pollers.is_empty() && !job.is_finished()
+            }
+            _ => false,
+        };
+
+        if abandoned {
+            info!(target: "compiler", "Nobody is waiting for job {}, cancelling it", job_id);
+            self.cancel_locked(jobs, job_id);
+        }
+
+        result
+    }
+
+    // Stores the timings of the build that ran for a job
+    pub fn set_job_metrics(&self, job_id: &str, metrics: BuildMetrics) {
+        self.record_duration(metrics.duration_ms);
+        self.update_job(job_id, |job| job.metrics = Some(metrics));
+    }
+
+    // Keeps the duration of the last builds for the wait estimates
+    fn record_duration(&self, duration_ms: u64) {
+        push_sample(&self.recent_durations, duration_ms);
+    }
+
+    // Average duration of the last builds
+    pub fn average_build_duration(&self) -> Duration {
+        average_sample(&self.recent_durations)
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_BUILD_DURATION)
+    }
+
+    // Seconds until the given amount of builds is done by the workers, at least one
+    pub fn estimate_wait(&self, builds: usize) -> u64 {
+        let millis = self.average_build_duration().as_millis() as u64 * builds as u64;
+        let workers = self.workers as u64;
+
+        (millis / workers).div_ceil(1000).max(1)
+    }
+
+    // Applies a change to a job and wakes up everyone waiting for jobs to finish
+    fn update_job(&self, job_id: &str, update: impl FnOnce(&mut CompilationJob)) {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return;
+        }
+
+        let mut jobs =
+            jobs_res.expect("This will never panic because we checked for errors before");
+
+        match jobs.get_mut(job_id) {
+            Some(job) => update(job),
+            None => error!(target: "compiler", "Job {} not found", job_id),
+        }
+
+        self.job_finished.notify_all();
+    }
+
+    // Inserts a job, dropping the finished jobs that are past their retention time
+    fn insert_job(&self, job: CompilationJob) -> Result<(), String> {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return Err(String::from("Error queueing contract."));
+        }
+
+        let mut jobs =
+            jobs_res.expect("This will never panic because we checked for errors before");
+
+        Self::prune_jobs(&mut jobs);
+        jobs.insert(job.id.clone(), job);
+
+        Ok(())
+    }
+
+    // Inserts a job unless one for the same build key is still running
+    // Checking and inserting under the same lock makes sure concurrent requests share a single job
+    // New jobs are rejected once the queue or the address of the request is over its limit
+    fn insert_or_attach_job(&self, job: CompilationJob) -> Result<QueuedJob, QueueError> {
+        let jobs_res = self.jobs.lock();
+
+        if jobs_res.is_err() {
+            error!(target: "compiler", "Error locking jobs");
+            return Err(QueueError::Internal(String::from(
+                "Error queueing contract.",
+            )));
+        }
+
+        let mut jobs =
+            jobs_res.expect("This will never panic because we checked for errors before");
+
+        Self::prune_jobs(&mut jobs);
+
+        if let Some(running_job) = jobs.values_mut().find(|running_job| {
+            running_job.build_key == job.build_key
+                && running_job.kind == job.kind
+                && !running_job.is_finished()
+        }) {
+            // A polling client keeps the job alive for as long as the one that created it
+            let release_token = job.pollers.first().cloned();
+            running_job.pollers.extend(job.pollers);
+            return Ok(QueuedJob {
+                job_id: running_job.id.clone(),
+                attached: true,
+                release_token,
+            });
+        }
+
+        self.check_limits(&jobs, &job.address)?;
+
+        let job_id = job.id.clone();
+        let release_token = job.pollers.first().cloned();
+        jobs.insert(job.id.clone(), job);
+
+        Ok(QueuedJob {
+            job_id,
+            attached: false,
+            release_token,
+        })
+    }
+
+    // Checks a new job of the address fits in the queue
+    // Queued jobs are the ones waiting in the queue, so the jobs map is enough to count them
+    fn check_limits(
+        &self,
+        jobs: &HashMap<String, CompilationJob>,
+        address: &str,
+    ) -> Result<(), QueueError> {
+        let queued = jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Queued)
+            .count();
+        if self.limits.max_depth > 0 && queued >= self.limits.max_depth {
+            warn!(target: "compiler", "Compilation queue is full with {} jobs", queued);
+            // A slot is freed as soon as any worker takes the next job
+            return Err(QueueError::QueueFull {
+                retry_after: self.estimate_wait(1),
+            });
+        }
+
+        let outstanding: Vec<&CompilationJob> = jobs
+            .values()
+            .filter(|job| job.address == address && !job.is_finished())
+            .collect();
+        if self.limits.max_per_address > 0 && outstanding.len() >= self.limits.max_per_address {
+            warn!(target: "compiler", "Address {} has {} unfinished jobs", address, outstanding.len());
+            // Job ids grow in the order jobs are created, the oldest one of the address finishes first
+            let first_queued = outstanding
+                .iter()
+                .filter(|job| job.status == JobStatus::Queued)
+                .map(|job| &job.id)
+                .min();
+            let builds = match first_queued {
+                Some(first_id) => {
+                    let ahead = jobs
+                        .values()
+                        .filter(|job| job.status == JobStatus::Queued && &job.id < first_id)
+                        .count();
+                    ahead + self.workers
+                }
+                // Every job of the address is compiling already
+                None => self.workers,
+            };
+            return Err(QueueError::AddressLimit {
+                retry_after: self.estimate_wait(builds),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Drops the finished jobs that are past their retention time
+    fn prune_jobs(jobs: &mut HashMap<String, CompilationJob>) {
+        jobs.retain(|_, job| match job.finished_at {
+            Some(finished_at) => finished_at.elapsed() < JOB_RETENTION,
+            None => true,
+        });
+    }
+}
+
+// Priority class a request is queued in, checks are always high priority
+fn request_priority(wizard_message: &WizardMessage, kind: JobKind) -> JobPriority {
+    match kind {
+        JobKind::Check => JobPriority::High,
+        _ => wizard_message.priority.unwrap_or_default(),
+    }
+}
+
+// Saves a built contract in the db, where it is looked up by build key and downloaded from
+fn store_contract(db: &MongoRepo, contract: &Contract) {
+    match db.create_contract(contract) {
+        Ok(_) => {
+            info!(target: "compiler", "Contract {} saved in the database", &contract.code_id);
+        }
+        Err(_) => {
+            error!(target: "compiler", "There was an error saving the contract {} in the database", &contract.code_id);
+        }
+    }
+}
+
+// Random token a polling client releases its job with, job ids are not secret
+fn release_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+
+    Ok(hex::encode(bytes))
+}
+
+// Adds a sample to a rolling window of the last jobs
+fn push_sample(samples: &Mutex<VecDeque<u64>>, value: u64) {
+    match samples.lock() {
+        Ok(mut samples) => {
+            if samples.len() == RECENT_JOBS {
+                samples.pop_front();
+            }
+            samples.push_back(value);
+        }
+        Err(_) => error!(target: "compiler", "Error locking samples"),
+    }
+}
+
+// Average of a rolling window, none until the first sample
+fn average_sample(samples: &Mutex<VecDeque<u64>>) -> Option<u64> {
+    match samples.lock() {
+        Ok(samples) if !samples.is_empty() => {
+            Some(samples.iter().sum::<u64>() / samples.len() as u64)
+        }
+        Ok(_) => None,
+        Err(_) => {
+            error!(target: "compiler", "Error locking samples");
             None
-        } else {
-            Some(queue.remove(0))
         }
     }
 }
+
+#[cfg(test)]
+#[path = "../tests/utils/compilation_queue_tests.rs"]
+mod compilation_queue_tests;
@@ -1,36 +1,122 @@
 use log::{error, info};
-use std::fs::{copy, File};
+use std::cell::Cell;
+use std::ffi::OsStr;
+use std::fs::{copy, create_dir_all, read_dir, read_to_string, remove_dir_all, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::Output;
 use std::sync::atomic::AtomicBool;
-use std::{env, sync::Arc, thread};
-
-use crate::models::api_models::WizardMessage;
-use crate::models::db_models::Contract;
-
-use super::compilation_queue::CompilationQueue;
-
-// Compiler is a singleton that handles the compilation of contracts
+use std::time::{Duration, Instant, SystemTime};
+use std::{env, sync::Arc};
+
+use crate::models::api_models::{
+    BuildMode, BuildOptions, Diagnostic, ServerError, TestReport, WizardMessage,
+};
+use crate::models::db_models::{BuildMetrics, Contract, JobKind};
+
+use super::compilation_queue::{CompilationQueue, CompilationRequest};
+use super::diagnostics::parse_cargo_messages;
+use super::manifest::{manifest_key, render_manifest, MANIFEST_TEMPLATE, TEMPLATE_FEATURES};
+use super::process::{run_cancellable, RunError};
+use super::sandbox::Sandbox;
+use super::test_report::parse_test_output;
+use super::toolchains::{ToolchainProfile, Toolchains};
+use super::vendor::{render_cargo_config, VENDOR_CONFIG, VENDOR_LOCKFILE};
+
+// Files copied from the template dir of a toolchain profile into every worker workspace
+const WORKSPACE_FILES: [&str; 3] = [MANIFEST_TEMPLATE, "Cargo.lock", "template-lib.rs"];
+
+// Outputs cargo contract leaves in target/ink, removed before every build so they are never stale
+const CONTRACT_OUTPUTS: [&str; 3] = [
+    "compiled_contract.wasm",
+    "compiled_contract.json",
+    "compiled_contract.contract",
+];
+
+// Number of compiler workers used when COMPILER_WORKERS is not set
+pub const DEFAULT_COMPILER_WORKERS: usize = 1;
+
+// Seconds a job can run cargo for when COMPILATION_TIMEOUT is not set
+pub const DEFAULT_COMPILATION_TIMEOUT: u64 = 300;
+
+// Cargo target dirs a worker keeps per toolchain profile when MAX_TARGET_DIRS is not set
+pub const DEFAULT_MAX_TARGET_DIRS: usize = 8;
+
+// Compiler is a worker that handles the compilation of contracts
+// Every worker owns an isolated copy of the template crate of each toolchain profile
 pub struct Compiler {
-    pub cargo_loc: String,
+    pub worker_id: usize,
+    pub toolchains: Arc<Toolchains>,
+    // Sandbox of this worker, its builds run as a uid no other worker uses
+    pub sandbox: Sandbox,
     pub compilation_queue: Arc<CompilationQueue>,
     pub shutdown_flag: Arc<AtomicBool>,
     pub dir_path: PathBuf,
+    // Wall-clock limit of a job, shared by every cargo command the job runs
+    pub timeout: Duration,
+    // When the job being processed runs out of time
+    pub deadline: Cell<Instant>,
+    // Target dirs kept per profile, the least recently used ones are removed over it. 0 keeps them all
+    pub max_target_dirs: usize,
+    // Set by the queue when the job of the worker is cancelled, kills the running cargo process
+    pub cancelled: Arc<AtomicBool>,
 }
 
-// Compiler implementation
-impl Compiler {
-    // Initializes the compiler
-    pub fn init(compilation_queue: Arc<CompilationQueue>, shutdown_flag: Arc<AtomicBool>) -> Self {
-        info!(target: "compiler", "Initializing compiler");
-        let cargo_loc = match env::var("CARGO") {
-            Ok(v) => v.to_string(),
+// Reads the amount of compiler workers to spawn from the COMPILER_WORKERS env variable
+pub fn get_compiler_workers() -> usize {
+    match env::var("COMPILER_WORKERS") {
+        Ok(v) => match v.parse::<usize>() {
+            Ok(workers) if workers > 0 => workers,
+            _ => {
+                error!(target: "compiler", "Invalid COMPILER_WORKERS value: {}", v);
+                DEFAULT_COMPILER_WORKERS
+            }
+        },
+        Err(_) => DEFAULT_COMPILER_WORKERS,
+    }
+}
+
+// Reads the wall-clock limit of every job from the COMPILATION_TIMEOUT env variable
+pub fn get_compilation_timeout() -> Duration {
+    let seconds = match env::var("COMPILATION_TIMEOUT") {
+        Ok(v) => match v.parse::<u64>() {
+            Ok(seconds) if seconds > 0 => seconds,
+            _ => {
+                error!(target: "compiler", "Invalid COMPILATION_TIMEOUT value: {}", v);
+                DEFAULT_COMPILATION_TIMEOUT
+            }
+        },
+        Err(_) => DEFAULT_COMPILATION_TIMEOUT,
+    };
+
+    Duration::from_secs(seconds)
+}
+
+// Reads the amount of target dirs kept per worker and profile from the MAX_TARGET_DIRS env variable
+pub fn get_max_target_dirs() -> usize {
+    match env::var("MAX_TARGET_DIRS") {
+        Ok(v) => match v.parse::<usize>() {
+            Ok(max) => max,
             Err(_) => {
-                error!(target: "compiler", "CARGO environment variable not set");
-                std::process::exit(1);
+                error!(target: "compiler", "Invalid MAX_TARGET_DIRS value: {}", v);
+                DEFAULT_MAX_TARGET_DIRS
             }
-        };
+        },
+        Err(_) => DEFAULT_MAX_TARGET_DIRS,
+    }
+}
+
+// Compiler implementation
+impl Compiler {
+    // Initializes the compiler worker and its workspaces
+    pub fn init(
+        worker_id: usize,
+        toolchains: Arc<Toolchains>,
+        sandbox: Arc<Sandbox>,
+        compilation_queue: Arc<CompilationQueue>,
+        shutdown_flag: Arc<AtomicBool>,
+    ) -> Self {
+        info!(target: "compiler", "Initializing compiler worker {}", worker_id);
 
         // Create the directory for the compiler
         let current_dir = env::current_dir();
@@ -42,14 +128,87 @@ impl Compiler {
         let current_dir =
             current_dir.expect("This will never panic because we checked for errors before");
 
-        let dir_path = current_dir.join("compilation_target");
+        let dir_path = current_dir
+            .join("compilation_workspaces")
+            .join(format!("worker_{}", worker_id));
+
+        // A workspace per toolchain profile, each one built from the profile template
+        // Workspaces belong to the uid the worker builds as, with the target dirs left by builds that ran as another one
+        let sandbox = sandbox.for_worker(worker_id);
+        for profile in &toolchains.profiles {
+            let workspace_path = dir_path.join(&profile.name);
+            if let Err(e) = Self::create_profile_workspace(&current_dir, profile, &workspace_path) {
+                error!(target: "compiler", "Error creating workspace {} for worker {}: {:?}", profile.name, worker_id, e);
+                std::process::exit(1);
+            }
+
+            if let Err(e) = sandbox.give_workspace(&workspace_path, true) {
+                error!(target: "compiler", "Error handing over workspace {} of worker {}: {:?}", profile.name, worker_id, e);
+                std::process::exit(1);
+            }
+        }
 
         Compiler {
-            cargo_loc,
+            worker_id,
+            toolchains,
+            sandbox,
             compilation_queue,
             shutdown_flag,
             dir_path,
+            timeout: get_compilation_timeout(),
+            deadline: Cell::new(Instant::now()),
+            max_target_dirs: get_max_target_dirs(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Creates the workspace of a toolchain profile from its template and vendor dirs
+    fn create_profile_workspace(
+        current_dir: &Path,
+        profile: &ToolchainProfile,
+        workspace_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let template_path = current_dir.join(&profile.template_dir);
+        let vendor_path = profile
+            .vendor_dir
+            .as_ref()
+            .map(|vendor_dir| current_dir.join(vendor_dir));
+
+        Self::create_workspace(&template_path, vendor_path.as_deref(), workspace_path)
+    }
+
+    // Copies the template crate of a toolchain profile into a worker workspace
+    // Vendored profiles use the vendored Cargo.lock and a cargo config that replaces every source
+    fn create_workspace(
+        template_path: &Path,
+        vendor_path: Option<&Path>,
+        dir_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        create_dir_all(dir_path)?;
+
+        for file in WORKSPACE_FILES {
+            let source_file_path = template_path.join(file);
+            // Cargo.lock is optional, cargo will generate it if missing
+            if source_file_path.exists() {
+                copy(source_file_path, dir_path.join(file))?;
+            }
         }
+
+        if let Some(vendor_path) = vendor_path {
+            copy(
+                vendor_path.join(VENDOR_LOCKFILE),
+                dir_path.join("Cargo.lock"),
+            )?;
+
+            let vendor_config = read_to_string(vendor_path.join(VENDOR_CONFIG))?;
+            let cargo_config = render_cargo_config(&vendor_config, vendor_path)?;
+
+            create_dir_all(dir_path.join(".cargo"))?;
+            let mut config_file = File::create(dir_path.join(".cargo").join("config.toml"))?;
+            config_file.write_all(cargo_config.as_bytes())?;
+        }
+
+        Ok(())
     }
 
     // Main compiler function
@@ -59,178 +218,669 @@ impl Compiler {
     // Stage 3.- Shutdown
     pub fn start(&self) {
         // Stage 1
-        // Create the directory for the compiler
-        let source_file_path = &self.dir_path.join("template-lib.rs");
-        let destination_file_path = &self.dir_path.join("lib.rs");
-
-        // Copy the file and rename it
-        let copy_res = copy(source_file_path, destination_file_path);
-        if copy_res.is_err() {
-            error!(target: "compiler", "Error copying template-lib.rs to lib.rs");
+        // Compile the template contract of every profile to warm up the workspaces
+        for profile in &self.toolchains.profiles {
+            self.compile_template_contract(profile);
         }
 
-        // Compile init contract
-        let res = self.compile_contract();
+        // Stage 2.-
+        // Loop and compile requests until shutdown flag is set
+        // Idle workers block until a request is queued or the server shuts down
+        while let Some(request) = self.compilation_queue.wait_for_request(&self.shutdown_flag) {
+            // Perform the compilation for the request here
+            info!(target: "compiler",
+                "Worker {} compiling code for user: {}",
+                self.worker_id,
+                request.wizard_message.address
+            );
+
+            // A job cancelled before this worker registered its flag is skipped
+            if !self
+                .compilation_queue
+                .watch_running_job(&request.job_id, &self.cancelled)
+            {
+                info!(target: "compiler", "Job {} was cancelled before it started", request.job_id);
+                continue;
+            }
 
-        if res.is_err() {
-            self.delete_compilation_files();
-            error!(target: "compiler", "Error compiling init contract");
+            let toolchain = request.wizard_message.toolchain.clone();
+            self.process_request(request);
+
+            // A cancelled job leaves its files half written, the workspace is restored for the next job
+            if self.cancelled.load(std::sync::atomic::Ordering::Relaxed) {
+                self.reset_workspace(toolchain.as_deref());
+            }
         }
 
-        // Stage 2.-
-        // Loop and compile requests until shutdown flag is set
-        while !self
-            .shutdown_flag
-            .load(std::sync::atomic::Ordering::Relaxed)
-        {
-            // Take a request from the queue
-            let request = self.compilation_queue.take_request();
-
-            // Checking if there's something to do
-            if let Some(request) = request {
-                // Perform the compilation for the request here
-                info!(target: "compiler",
-                    "Compiling code for user: {}",
-                    request.wizard_message.address
+        // Stage 3 .-
+        // Shutdown gracefully
+        info!(target: "compiler", "Compiler worker {} shutting down...", self.worker_id);
+        // The target dirs are kept so the build cache is still warm after a restart
+        self.compilation_queue
+            .fail_queued_requests("Compiler shutting down.");
+        info!(target: "compiler", "Compiler worker {} shutdown complete", self.worker_id);
+    }
+
+    // Compiles, checks or tests the contract of a request and finishes its job
+    fn process_request(&self, request: CompilationRequest) {
+        let wizard_message = request.wizard_message;
+        self.deadline.set(Instant::now() + self.timeout);
+
+        // Resolve the toolchain profile the contract is built with
+        let profile = match self.toolchains.get(wizard_message.toolchain.as_deref()) {
+            Ok(profile) => profile,
+            Err(error_msg) => {
+                error!(target: "compiler", "{}", error_msg);
+                self.compilation_queue
+                    .finish_job(&request.job_id, Err(ServerError::new(error_msg)));
+
+                return;
+            }
+        };
+
+        let manifest = match self.create_contract_files(profile, &wizard_message) {
+            Ok(manifest) => manifest,
+            Err(_) => {
+                error!(target: "compiler", "Error creating files");
+                self.compilation_queue.finish_job(
+                    &request.job_id,
+                    Err(ServerError::new(String::from("Error creating files."))),
                 );
 
-                let wizard_message = request.wizard_message;
+                return;
+            }
+        };
 
-                let compile_res = self.create_contract_files(&wizard_message);
+        let target_dir = self.use_target_dir(profile, &manifest);
 
-                if compile_res.is_err() {
-                    self.delete_compilation_files();
-                    error!(target: "compiler", "Error creating files");
-                    let msg_res = request.tx.send(Err(String::from("Error creating files.")));
+        // Checks and tests only report their results, no artifact is produced
+        match request.kind {
+            JobKind::Check => {
+                let res = self.check_contract(
+                    profile,
+                    &target_dir,
+                    &wizard_message.build_options.features,
+                );
+                self.compilation_queue
+                    .finish_check_job(&request.job_id, res);
 
-                    if msg_res.is_err() {
-                        error!(target: "compiler", "Error sending message");
-                    }
+                return;
+            }
+            JobKind::Test => {
+                let res = self.test_contract(
+                    profile,
+                    &target_dir,
+                    &wizard_message.build_options.features,
+                );
+                self.compilation_queue.finish_test_job(&request.job_id, res);
 
-                    continue;
-                }
+                return;
+            }
+            JobKind::Build => (),
+        }
 
-                // Compile contract
-                let res = self.compile_contract();
+        // Compile contract
+        let (res, metrics) =
+            self.timed_compile(profile, &target_dir, &wizard_message.build_options);
+        self.compilation_queue
+            .set_job_metrics(&request.job_id, metrics);
 
-                // Evaluate compilation result
-                if res.is_err() {
-                    error!(target: "compiler", "Error compiling contract");
-                    let msg_res = request
-                        .tx
-                        .send(Err(String::from("Error compiling contract.")));
+        // Evaluate compilation result
+        if let Err(compilation_error) = res {
+            error!(target: "compiler", "Error compiling contract");
+            self.compilation_queue
+                .finish_job(&request.job_id, Err(compilation_error));
 
-                    if msg_res.is_err() {
-                        error!(target: "compiler", "Error sending message");
-                    }
+            return;
+        }
 
-                    continue;
-                }
+        // Get contract data
+        let contract =
+            self.get_contract_data(profile, &target_dir, &request.code_id, &request.build_key);
 
-                // Get contract data
-                let contract = self.get_contract_data(&request.code_id);
+        if contract.is_err() {
+            error!(target: "compiler", "Error getting contract data");
+            self.compilation_queue.finish_job(
+                &request.job_id,
+                Err(ServerError::new(String::from(
+                    "Error getting contract data.",
+                ))),
+            );
 
-                if contract.is_err() {
-                    error!(target: "compiler", "Error getting contract data");
-                    let msg_res = request
-                        .tx
-                        .send(Err(String::from("Error getting contract data.")));
+            return;
+        }
 
-                    if msg_res.is_err() {
-                        error!(target: "compiler", "Error sending message");
-                    }
+        let mut contract =
+            contract.expect("This will not panic because we already checked for errors");
 
-                    continue;
-                }
+        // Lint the contract once it is known to build
+        if wizard_message.lint {
+            match self.lint_contract(profile, &target_dir, &wizard_message.build_options.features) {
+                Ok(lints) => contract.lints = Some(lints),
+                Err(lint_error) => {
+                    self.compilation_queue
+                        .finish_job(&request.job_id, Err(lint_error));
 
-                let msg_res =
-                    request.tx.send(Ok(contract
-                        .expect("This will not panic because we already checked for errors")));
-                if msg_res.is_err() {
-                    error!(target: "compiler", "Error sending message");
+                    return;
                 }
-            } else {
-                thread::sleep(std::time::Duration::from_millis(100));
             }
         }
 
-        // Stage 3 .-
-        // Shutdown gracefully
-        info!(target: "compiler", "Compiler shutting down...");
-        self.delete_compilation_files();
-        info!(target: "compiler", "Compiler shutdown complete");
+        self.compilation_queue
+            .finish_job(&request.job_id, Ok(contract));
+    }
+
+    // Restores the workspace of a profile after a cancelled job killed cargo half way
+    // The contract files are removed and the template files copied again, the target dir is kept warm
+    fn reset_workspace(&self, toolchain: Option<&str>) {
+        let profile = match self.toolchains.get(toolchain) {
+            Ok(profile) => profile,
+            Err(_) => return,
+        };
+        let workspace_path = self.workspace_path(profile);
+        info!(target: "compiler", "Worker {} resetting workspace {}", self.worker_id, profile.name);
+
+        let contract_path = workspace_path.join("lib.rs");
+        if contract_path.exists() {
+            if let Err(e) = std::fs::remove_file(contract_path) {
+                error!(target: "compiler", "Error deleting files: {:?}", e);
+            }
+        }
+
+        let metadata_folder = workspace_path.join("__openbrush_metadata_folder");
+        if metadata_folder.exists() {
+            if let Err(e) = std::fs::remove_dir_all(metadata_folder) {
+                error!(target: "compiler", "Error deleting files: {:?}", e);
+            }
+        }
+
+        let res = env::current_dir()
+            .map_err(|e| e.into())
+            .and_then(|current_dir| {
+                Self::create_profile_workspace(&current_dir, profile, &workspace_path)
+            });
+        if let Err(e) = res {
+            error!(target: "compiler", "Error resetting workspace {} for worker {}: {:?}", profile.name, self.worker_id, e);
+        }
+    }
+
+    // Copies the template-lib.rs of a profile into its workspace and compiles it
+    fn compile_template_contract(&self, profile: &ToolchainProfile) {
+        self.deadline.set(Instant::now() + self.timeout);
+        let workspace_path = self.workspace_path(profile);
+        let source_file_path = workspace_path.join("template-lib.rs");
+        let destination_file_path = workspace_path.join("lib.rs");
+
+        // Copy the file and rename it
+        let copy_res = copy(source_file_path, destination_file_path);
+        if copy_res.is_err() {
+            error!(target: "compiler", "Error copying template-lib.rs to lib.rs");
+        }
+
+        // Render the manifest with the features used by the template
+        let template_features: Vec<String> =
+            TEMPLATE_FEATURES.iter().map(|f| f.to_string()).collect();
+        let manifest = match self.write_manifest(profile, &template_features) {
+            Ok(manifest) => manifest,
+            Err(_) => {
+                error!(target: "compiler", "Error rendering Cargo.toml for the init contract");
+                return;
+            }
+        };
+
+        // Compile init contract
+        let (res, _) = self.timed_compile(
+            profile,
+            &self.use_target_dir(profile, &manifest),
+            &BuildOptions::default(),
+        );
+
+        if res.is_err() {
+            error!(target: "compiler", "Error compiling init contract for {}", profile.name);
+        }
+    }
+
+    // Compiles the contract measuring how long the build took and if the cache was warm
+    fn timed_compile(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        options: &BuildOptions,
+    ) -> (Result<(), ServerError>, BuildMetrics) {
+        let warm_cache = target_dir.exists();
+        let started_at = Instant::now();
+
+        let res = self.compile_contract(profile, target_dir, options);
+
+        let metrics = BuildMetrics {
+            warm_cache,
+            duration_ms: started_at.elapsed().as_millis() as u64,
+        };
+        info!(target: "compiler",
+            "Worker {} build took {} ms with a {} cache",
+            self.worker_id,
+            metrics.duration_ms,
+            if warm_cache { "warm" } else { "cold" }
+        );
+
+        (res, metrics)
+    }
+
+    // Directory where the contracts of a toolchain profile are built by this worker
+    fn workspace_path(&self, profile: &ToolchainProfile) -> PathBuf {
+        self.dir_path.join(&profile.name)
+    }
+
+    // Cargo target dir of a feature set, so switching features never rebuilds the dependencies
+    fn target_dir(&self, profile: &ToolchainProfile, manifest: &str) -> PathBuf {
+        self.workspace_path(profile)
+            .join("targets")
+            .join(manifest_key(manifest))
+    }
+
+    // Target dir of a feature set about to be built, marked as the most recently used
+    // Every target dir holds a full build of the dependencies, the least recently used are removed
+    // so a worker never keeps more than max_target_dirs of them per profile
+    fn use_target_dir(&self, profile: &ToolchainProfile, manifest: &str) -> PathBuf {
+        let target_dir = self.target_dir(profile, manifest);
+
+        // Only existing dirs are touched, timed_compile tells cold builds by the dir missing
+        if target_dir.exists() {
+            let touched =
+                File::open(&target_dir).and_then(|dir| dir.set_modified(SystemTime::now()));
+            if let Err(e) = touched {
+                error!(target: "compiler", "Error updating {}: {:?}", target_dir.display(), e);
+            }
+        }
+
+        if self.max_target_dirs > 0 {
+            let targets_path = self.workspace_path(profile).join("targets");
+            if let Err(e) = evict_target_dirs(&targets_path, &target_dir, self.max_target_dirs) {
+                error!(target: "compiler", "Error removing target dirs in {}: {:?}", targets_path.display(), e);
+            }
+        }
+
+        target_dir
     }
 
     // Function called by the compiler to generate the contract wasm and metadata
-    fn compile_contract(&self) -> Result<(), Box<dyn std::error::Error>> {
+    // When the build fails the error carries the diagnostics reported by rustc
+    fn compile_contract(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        options: &BuildOptions,
+    ) -> Result<(), ServerError> {
+        Self::remove_stale_outputs(&self.workspace_path(profile), target_dir);
+
+        // Vendored profiles already have every dependency on disk
+        if profile.vendor_dir.is_none() {
+            self.fetch_dependencies(profile)?;
+        }
+
         // This is the command used to compile the contract
-        let mut binding = Command::new(self.cargo_loc.clone());
-        let compiler_cmd = binding
-            .arg("contract")
-            .arg("build")
-            .arg("--release")
-            .arg("--quiet")
-            .current_dir(self.dir_path.clone());
+        let output =
+            match self.run_cargo(profile, target_dir, &contract_build_args(profile, options)) {
+                Ok(output) => output,
+                Err(RunError::TimedOut) => {
+                    // Cargo recovers from builds killed half way, so the cache is kept
+                    error!(target: "compiler", "Compilation timed out after {:?}", self.timeout);
+                    return Err(ServerError::new(String::from("Compilation timed out.")));
+                }
+                Err(RunError::Cancelled) => return Err(job_cancelled()),
+                Err(e) => {
+                    error!(target: "compiler", "Error running cargo contract: {}", e);
+                    return Err(ServerError::new(String::from("Error compiling contract.")));
+                }
+            };
 
         // Check the status of the command execution
-        let status = compiler_cmd.status()?;
-        if !status.success() {
+        if !output.status.success() {
             error!(target: "compiler", "Compilation failed");
-            return Err("Compilation failed".into());
+            error!(target: "compiler", "stdout: {}", String::from_utf8_lossy(&output.stdout));
+            error!(target: "compiler", "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+            return Err(ServerError::with_diagnostics(
+                String::from("Error compiling contract."),
+                self.collect_diagnostics(profile, target_dir, &options.features),
+            ));
         }
         info!(target: "compiler", "Compilation success");
 
         Ok(())
     }
 
-    // This function is used to create the contract files in the filesystem
+    // Collects the diagnostics explaining why a build failed
+    fn collect_diagnostics(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        features: &[String],
+    ) -> Vec<Diagnostic> {
+        match self.check_contract(profile, target_dir, features) {
+            Ok(diagnostics) => diagnostics,
+            Err(check_error) => check_error.diagnostics.unwrap_or_default(),
+        }
+    }
+
+    // Type checks the contract asking cargo for the rustc diagnostics in JSON format
+    // No wasm is generated, warnings are returned when the check passes
+    fn check_contract(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        features: &[String],
+    ) -> Result<Vec<Diagnostic>, ServerError> {
+        // Vendored profiles already have every dependency on disk
+        if profile.vendor_dir.is_none() {
+            self.fetch_dependencies(profile)?;
+        }
+
+        let mut args = vec![
+            String::from("check"),
+            String::from(offline_flag(profile)),
+            String::from("--message-format=json"),
+        ];
+        args.extend(features_arg(features));
+
+        let output = match self.run_cargo(profile, target_dir, &args) {
+            Ok(output) => output,
+            Err(RunError::TimedOut) => {
+                error!(target: "compiler", "Check timed out after {:?}", self.timeout);
+                return Err(ServerError::new(String::from("Check timed out.")));
+            }
+            Err(RunError::Cancelled) => return Err(job_cancelled()),
+            Err(e) => {
+                error!(target: "compiler", "Error running cargo check: {}", e);
+                return Err(ServerError::new(String::from("Error checking contract.")));
+            }
+        };
+
+        let diagnostics = parse_cargo_messages(&String::from_utf8_lossy(&output.stdout));
+
+        if !output.status.success() {
+            info!(target: "compiler", "Check failed with {} diagnostics", diagnostics.len());
+            return Err(ServerError::with_diagnostics(
+                String::from("Contract check failed."),
+                diagnostics,
+            ));
+        }
+        info!(target: "compiler", "Check success");
+
+        Ok(diagnostics)
+    }
+
+    // Lints the contract with clippy and, when the profile has it, the ink! linter cargo contract build --lint runs
+    // Lints denied by the contract fail the run but are still findings, the job only fails when the linters can't run
+    fn lint_contract(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        features: &[String],
+    ) -> Result<Vec<Diagnostic>, ServerError> {
+        let offline_flag = offline_flag(profile);
+
+        let mut clippy_args = vec![
+            String::from("clippy"),
+            String::from(offline_flag),
+            String::from("--message-format=json"),
+        ];
+        clippy_args.extend(features_arg(features));
+        let mut lints = self.run_linter(profile, target_dir, &clippy_args)?;
+
+        if profile.ink_linting {
+            let mut dylint_args = vec![
+                String::from("dylint"),
+                String::from("--lib=ink_linting"),
+                String::from("--"),
+                String::from(offline_flag),
+                String::from("--message-format=json"),
+            ];
+            dylint_args.extend(features_arg(features));
+            lints.extend(self.run_linter(profile, target_dir, &dylint_args)?);
+        }
+        info!(target: "compiler", "Lint finished with {} findings", lints.len());
+
+        Ok(lints)
+    }
+
+    // Runs one linter and returns its findings
+    fn run_linter(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        args: &[String],
+    ) -> Result<Vec<Diagnostic>, ServerError> {
+        let output = match self.run_cargo(profile, target_dir, args) {
+            Ok(output) => output,
+            Err(RunError::TimedOut) => {
+                error!(target: "compiler", "Lint timed out after {:?}", self.timeout);
+                return Err(ServerError::new(String::from("Lint timed out.")));
+            }
+            Err(RunError::Cancelled) => return Err(job_cancelled()),
+            Err(e) => {
+                error!(target: "compiler", "Error running cargo {}: {}", args[0], e);
+                return Err(ServerError::new(String::from("Error linting contract.")));
+            }
+        };
+
+        let lints = parse_cargo_messages(&String::from_utf8_lossy(&output.stdout));
+
+        // A failed run without findings means the linter itself failed
+        if !output.status.success() && lints.is_empty() {
+            error!(target: "compiler", "Error running cargo {}", args[0]);
+            error!(target: "compiler", "stderr: {}", String::from_utf8_lossy(&output.stderr));
+            return Err(ServerError::new(String::from("Error linting contract.")));
+        }
+
+        Ok(lints)
+    }
+
+    // Runs the unit tests of the contract off-chain inside the sandbox
+    // Failing tests are part of the report, the job only fails when the tests can't be built
+    fn test_contract(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        features: &[String],
+    ) -> Result<TestReport, ServerError> {
+        // Vendored profiles already have every dependency on disk
+        if profile.vendor_dir.is_none() {
+            self.fetch_dependencies(profile)?;
+        }
+
+        let mut args = vec![
+            String::from("test"),
+            String::from(offline_flag(profile)),
+            String::from("--message-format=json"),
+        ];
+        args.extend(features_arg(features));
+        args.extend([String::from("--"), String::from("--show-output")]);
+
+        let output = match self.run_cargo(profile, target_dir, &args) {
+            Ok(output) => output,
+            Err(RunError::TimedOut) => {
+                error!(target: "compiler", "Tests timed out after {:?}", self.timeout);
+                return Err(ServerError::new(String::from("Tests timed out.")));
+            }
+            Err(RunError::Cancelled) => return Err(job_cancelled()),
+            Err(e) => {
+                error!(target: "compiler", "Error running cargo test: {}", e);
+                return Err(ServerError::new(String::from("Error running tests.")));
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let report = parse_test_output(&stdout);
+
+        // A failed run without any test result means the tests didn't compile
+        if !output.status.success() && report.tests.is_empty() {
+            error!(target: "compiler", "Error compiling tests");
+            return Err(ServerError::with_diagnostics(
+                String::from("Error compiling tests."),
+                parse_cargo_messages(&stdout),
+            ));
+        }
+        info!(target: "compiler",
+            "Tests finished: {} passed, {} failed, {} ignored",
+            report.passed,
+            report.failed,
+            report.ignored
+        );
+
+        Ok(report)
+    }
+
+    // Downloads the dependencies of the contract outside the sandbox, which has no network
+    // Fetching doesn't run any build script or macro, so no contract code is executed here
+    fn fetch_dependencies(&self, profile: &ToolchainProfile) -> Result<(), ServerError> {
+        let mut command = profile.cargo_command();
+        command
+            .arg("fetch")
+            .current_dir(self.workspace_path(profile));
+
+        match run_cancellable(&mut command, self.time_left(), &self.cancelled) {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => {
+                error!(target: "compiler", "Error fetching dependencies: {}", String::from_utf8_lossy(&output.stderr));
+                Err(ServerError::new(String::from(
+                    "Error fetching dependencies.",
+                )))
+            }
+            Err(e) => {
+                error!(target: "compiler", "Error fetching dependencies: {}", e);
+                Err(ServerError::new(String::from(
+                    "Error fetching dependencies.",
+                )))
+            }
+        }
+    }
+
+    // Time the job being processed has left, so a failed build followed by a check never runs past the timeout
+    fn time_left(&self) -> Duration {
+        self.deadline
+            .get()
+            .saturating_duration_since(Instant::now())
+    }
+
+    // Runs cargo with the profile toolchain inside the sandbox capturing stdout and stderr
+    // The workspace is the only writable dir and the command is killed once the job runs out of time
+    fn run_cargo<S: AsRef<OsStr>>(
+        &self,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        args: &[S],
+    ) -> Result<Output, RunError> {
+        let workspace_path = self.workspace_path(profile);
+        let mut command = profile.cargo_command();
+        command
+            .args(args)
+            .env("CARGO_TARGET_DIR", target_dir)
+            .current_dir(&workspace_path);
+
+        // The contract files were just written by the server
+        if let Err(e) = self.sandbox.give_workspace(&workspace_path, false) {
+            error!(target: "compiler", "Error handing over workspace {}: {:?}", workspace_path.display(), e);
+        }
+
+        let mut command = self
+            .sandbox
+            .wrap(command, &workspace_path, &profile.sandbox_dirs());
+        run_cancellable(&mut command, self.time_left(), &self.cancelled)
+    }
+
+    // This function is used to create the contract files in the filesystem, returning the manifest
     fn create_contract_files(
         &self,
+        profile: &ToolchainProfile,
         wizard_message: &WizardMessage,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let path = self.dir_path.join("lib.rs");
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let path = self.workspace_path(profile).join("lib.rs");
         let mut lib_rs_file = File::create(path)?;
         lib_rs_file.write_all(wizard_message.code.as_bytes())?;
 
         info!(target: "compiler", "lib.rs successfully created");
 
-        Ok(())
+        self.write_manifest(profile, &wizard_message.features)
     }
 
-    // This function is used to delete the compiled contract files in the filesystem
-    fn delete_compilation_files(&self) {
-        let res = std::fs::remove_dir_all(self.dir_path.join("target"));
-        if res.is_err() {
-            error!(target: "compiler", "Error deleting files: {:?}", res);
+    // This function renders the Cargo.toml enabling only the requested features
+    // The file is left untouched when the features didn't change to avoid needless cargo work
+    fn write_manifest(
+        &self,
+        profile: &ToolchainProfile,
+        features: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let workspace_path = self.workspace_path(profile);
+        let template = read_to_string(workspace_path.join(MANIFEST_TEMPLATE))?;
+        let manifest = render_manifest(&template, features);
+
+        let path = workspace_path.join("Cargo.toml");
+        if read_to_string(&path).ok().as_ref() == Some(&manifest) {
+            return Ok(manifest);
         }
 
-        let res = std::fs::remove_dir_all(self.dir_path.join("__openbrush_metadata_folder"));
-        if res.is_err() {
-            error!(target: "compiler", "Error deleting files: {:?}", res);
+        let mut manifest_file = File::create(path)?;
+        manifest_file.write_all(manifest.as_bytes())?;
+
+        info!(target: "compiler", "Cargo.toml successfully created");
+
+        Ok(manifest)
+    }
+
+    // Removes the outputs of the previous contract so a failed build can never return them
+    // Dependency artifacts in the target dir are kept warm for the next builds
+    fn remove_stale_outputs(workspace_path: &Path, target_dir: &Path) {
+        for output in CONTRACT_OUTPUTS {
+            let path = target_dir.join("ink").join(output);
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(path) {
+                    error!(target: "compiler", "Error deleting files: {:?}", e);
+                }
+            }
+        }
+
+        let metadata_folder = workspace_path.join("__openbrush_metadata_folder");
+        if metadata_folder.exists() {
+            if let Err(e) = std::fs::remove_dir_all(metadata_folder) {
+                error!(target: "compiler", "Error deleting files: {:?}", e);
+            }
         }
     }
 
     // this function is used to read from the file system the wasm and metadata files generated by the compiler
     pub fn get_contract_data(
         &self,
-        code_id: &String,
+        profile: &ToolchainProfile,
+        target_dir: &Path,
+        code_id: &str,
+        build_key: &str,
     ) -> Result<Contract, Box<dyn std::error::Error>> {
         // Read compiled contract
-        let mut wasm_file = File::open(self.dir_path.join("target/ink/compiled_contract.wasm"))?;
+        let mut wasm_file = File::open(target_dir.join("ink/compiled_contract.wasm"))?;
         let mut wasm = Vec::new();
         wasm_file.read_to_end(&mut wasm)?;
 
         // Read contract metadata
-        let mut metadata_file =
-            File::open(self.dir_path.join("target/ink/compiled_contract.json"))?;
+        let mut metadata_file = File::open(target_dir.join("ink/compiled_contract.json"))?;
         let mut metadata = String::new();
         metadata_file.read_to_string(&mut metadata)?;
 
+        // Read the bundle with metadata and wasm that can be uploaded as is
+        let bundle = read_to_string(target_dir.join("ink/compiled_contract.contract"))?;
+
+        // Read the lock file the build resolved
+        let cargo_lock = read_to_string(self.workspace_path(profile).join("Cargo.lock"))?;
+
         let contract = Contract {
             id: None,
             code_id: code_id.to_owned(),
+            build_key: build_key.to_owned(),
             metadata,
             wasm,
+            bundle,
+            cargo_lock,
+            lints: None,
         };
         info!(target: "compiler", "get_contract_data success");
 
@@ -238,6 +888,78 @@ impl Compiler {
     }
 }
 
+// Arguments of cargo contract build for the options of a request, release builds unless asked otherwise
+fn contract_build_args(profile: &ToolchainProfile, options: &BuildOptions) -> Vec<String> {
+    let mut args = vec![
+        String::from("contract"),
+        String::from("build"),
+        String::from("--quiet"),
+        String::from(offline_flag(profile)),
+    ];
+
+    if options.mode == BuildMode::Release {
+        args.push(String::from("--release"));
+    }
+    if let Some(passes) = &options.optimization_passes {
+        args.push(format!("--optimization-passes={}", passes));
+    }
+    if options.keep_debug_symbols {
+        args.push(String::from("--keep-debug-symbols"));
+    }
+    args.extend(features_arg(&options.features));
+
+    args
+}
+
+// Flag keeping cargo off the network in every cargo command run inside the sandbox
+// The vendored Cargo.lock covers every feature set, so vendored profiles must never change it
+fn offline_flag(profile: &ToolchainProfile) -> &'static str {
+    if profile.vendor_dir.is_some() {
+        "--frozen"
+    } else {
+        "--offline"
+    }
+}
+
+// Flag enabling the requested features of the contract crate, if any
+fn features_arg(features: &[String]) -> Option<String> {
+    if features.is_empty() {
+        return None;
+    }
+
+    Some(format!("--features={}", features.join(",")))
+}
+
+// Removes the least recently used target dirs so at most max are left, counting the one in use
+pub fn evict_target_dirs(targets_path: &Path, in_use: &Path, max: usize) -> std::io::Result<()> {
+    if !targets_path.is_dir() {
+        return Ok(());
+    }
+
+    let mut dirs: Vec<(SystemTime, PathBuf)> = read_dir(targets_path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path != in_use)
+        .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+        .collect();
+
+    let excess = (dirs.len() + 1).saturating_sub(max);
+    dirs.sort();
+
+    for (_, dir) in dirs.into_iter().take(excess) {
+        info!(target: "compiler", "Removing least recently used target dir {}", dir.display());
+        remove_dir_all(dir)?;
+    }
+
+    Ok(())
+}
+
+// Error of a command killed because its job was cancelled, the job already holds the cancellation
+fn job_cancelled() -> ServerError {
+    info!(target: "compiler", "Cargo process killed, the job was cancelled");
+    ServerError::new(String::from("Job cancelled."))
+}
+
 #[cfg(test)]
 #[path = "../tests/utils/compiler_tests.rs"]
 mod compiler_tests;
@@ -0,0 +1,77 @@
+use crate::models::api_models::{Diagnostic, DiagnosticSeverity, DiagnosticSpan};
+use serde_json::Value;
+
+// Name of the file every submitted contract is written to
+pub const CONTRACT_FILE: &str = "lib.rs";
+
+// Name of the crate built from the compilation_target template
+pub const CONTRACT_CRATE: &str = "compiled_contract";
+
+// rustc summary messages that don't add anything to the list of diagnostics
+const SUMMARY_MESSAGES: [&str; 3] = ["aborting due to", "warning emitted", "warnings emitted"];
+
+// Parses the output of a cargo command run with --message-format=json
+// Only the errors and warnings of the contract crate are returned
+pub fn parse_cargo_messages(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| message["reason"] == "compiler-message")
+        .filter(|message| message["target"]["name"] == CONTRACT_CRATE)
+        .filter_map(|message| parse_compiler_message(&message["message"]))
+        .collect()
+}
+
+// Converts a rustc JSON diagnostic into a Diagnostic
+fn parse_compiler_message(message: &Value) -> Option<Diagnostic> {
+    let severity = match message["level"].as_str()? {
+        "error" | "error: internal compiler error" => DiagnosticSeverity::Error,
+        "warning" => DiagnosticSeverity::Warning,
+        _ => return None,
+    };
+
+    let text = message["message"].as_str()?.to_string();
+    let span = message["spans"].as_array().and_then(|spans| {
+        spans
+            .iter()
+            .find(|span| span["is_primary"] == true)
+            .and_then(contract_span)
+    });
+
+    if span.is_none()
+        && SUMMARY_MESSAGES
+            .iter()
+            .any(|summary| text.contains(summary))
+    {
+        return None;
+    }
+
+    Some(Diagnostic {
+        severity,
+        message: text,
+        code: message["code"]["code"].as_str().map(String::from),
+        span,
+    })
+}
+
+// Finds the span in lib.rs, following macro expansions back to their call site
+fn contract_span(span: &Value) -> Option<DiagnosticSpan> {
+    if span.is_null() {
+        return None;
+    }
+
+    if span["file_name"] == CONTRACT_FILE {
+        return Some(DiagnosticSpan {
+            line_start: span["line_start"].as_u64()? as usize,
+            line_end: span["line_end"].as_u64()? as usize,
+            column_start: span["column_start"].as_u64()? as usize,
+            column_end: span["column_end"].as_u64()? as usize,
+        });
+    }
+
+    contract_span(&span["expansion"]["span"])
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/diagnostics_tests.rs"]
+mod diagnostics_tests;
@@ -0,0 +1,155 @@
+use crate::models::db_models::JobPriority;
+use std::collections::{HashMap, VecDeque};
+
+use super::compilation_queue::CompilationRequest;
+
+// Requests waiting for a worker, grouped in priority classes taken from the highest one down
+// Within a class the addresses take turns, so an address flooding the service doesn't starve the rest
+#[derive(Default)]
+pub struct FairQueue {
+    classes: [PriorityClass; 3],
+}
+
+#[derive(Default)]
+struct PriorityClass {
+    // Addresses with requests waiting, in the order they get their next turn
+    turns: VecDeque<String>,
+    requests: HashMap<String, VecDeque<CompilationRequest>>,
+    len: usize,
+}
+
+impl FairQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds a request behind the other requests of its address
+    pub fn push(&mut self, request: CompilationRequest) {
+        self.classes[class_index(request.priority)].push(request);
+    }
+
+    // Takes the request of the next address in the highest priority class with requests waiting
+    pub fn pop(&mut self) -> Option<CompilationRequest> {
+        self.classes.iter_mut().find_map(PriorityClass::pop)
+    }
+
+    // Takes every request in the order they would be popped
+    pub fn drain(&mut self) -> Vec<CompilationRequest> {
+        std::iter::from_fn(|| self.pop()).collect()
+    }
+
+    // Takes a request out of the queue wherever it is, the other requests keep their turns
+    pub fn remove(&mut self, job_id: &str) -> Option<CompilationRequest> {
+        self.classes
+            .iter_mut()
+            .find_map(|class| class.remove(job_id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.iter().map(|class| class.len).sum()
+    }
+
+    // Amount of requests popped before the given one
+    pub fn requests_ahead(&self, job_id: &str) -> Option<usize> {
+        let mut ahead = 0;
+        for class in &self.classes {
+            match class.requests_ahead(job_id) {
+                Some(in_class) => return Some(ahead + in_class),
+                None => ahead += class.len,
+            }
+        }
+
+        None
+    }
+}
+
+impl PriorityClass {
+    fn push(&mut self, request: CompilationRequest) {
+        let address = request.wizard_message.address.clone();
+        let requests = self.requests.entry(address.clone()).or_default();
+
+        // An address gets a turn when its first request arrives
+        if requests.is_empty() {
+            self.turns.push_back(address);
+        }
+        requests.push_back(request);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<CompilationRequest> {
+        let address = self.turns.pop_front()?;
+        let requests = self.requests.get_mut(&address)?;
+        let request = requests.pop_front()?;
+
+        // The address goes back to the end of the line while it has requests left
+        if requests.is_empty() {
+            self.requests.remove(&address);
+        } else {
+            self.turns.push_back(address);
+        }
+        self.len -= 1;
+
+        Some(request)
+    }
+
+    fn remove(&mut self, job_id: &str) -> Option<CompilationRequest> {
+        let address = self
+            .turns
+            .iter()
+            .find(|address| {
+                self.requests[*address]
+                    .iter()
+                    .any(|request| request.job_id == job_id)
+            })?
+            .clone();
+        let requests = self.requests.get_mut(&address)?;
+        let index = requests
+            .iter()
+            .position(|request| request.job_id == job_id)?;
+        let request = requests.remove(index)?;
+
+        // An address without requests left loses its turn
+        if requests.is_empty() {
+            self.requests.remove(&address);
+            self.turns.retain(|turn| turn != &address);
+        }
+        self.len -= 1;
+
+        Some(request)
+    }
+
+    // Every turn pops one request of each address, so the n-th request of an address is popped
+    // in the n-th round, after the addresses in front of it had n + 1 turns and the others n
+    fn requests_ahead(&self, job_id: &str) -> Option<usize> {
+        let (slot, round) = self.turns.iter().enumerate().find_map(|(slot, address)| {
+            self.requests[address]
+                .iter()
+                .position(|request| request.job_id == job_id)
+                .map(|round| (slot, round))
+        })?;
+
+        let ahead = self
+            .turns
+            .iter()
+            .enumerate()
+            .map(|(other_slot, address)| {
+                let turns_before = if other_slot < slot { round + 1 } else { round };
+                self.requests[address].len().min(turns_before)
+            })
+            .sum();
+
+        Some(ahead)
+    }
+}
+
+fn class_index(priority: JobPriority) -> usize {
+    match priority {
+        JobPriority::High => 0,
+        JobPriority::Normal => 1,
+        JobPriority::Low => 2,
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/fair_queue_tests.rs"]
+mod fair_queue_tests;
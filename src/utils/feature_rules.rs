@@ -0,0 +1,182 @@
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::read_to_string;
+
+use super::sanity_check::{find_feature, CONTRACTS, FEATURES};
+
+// A rule on the features of a request, the reason is returned to the client when it is violated
+// Rules without a reason get one generated from the features they name
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum FeatureRule {
+    // When the feature is requested, or always when there's none, one of any_of must be requested too
+    Requires {
+        #[serde(default)]
+        feature: Option<String>,
+        any_of: Vec<String>,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    // At most one of the features can be requested
+    Conflicts {
+        features: Vec<String>,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+impl FeatureRule {
+    // Reason the rule is violated by the features, None when they follow it
+    pub fn violation(&self, features: &[String]) -> Option<String> {
+        let requested = |name: &String| features.contains(name);
+
+        match self {
+            FeatureRule::Requires {
+                feature,
+                any_of,
+                reason,
+            } => {
+                let applies = feature.as_ref().map_or(true, requested);
+                if !applies || any_of.iter().any(requested) {
+                    return None;
+                }
+
+                Some(reason.clone().unwrap_or_else(|| match feature {
+                    Some(feature) => {
+                        format!("Feature {} requires {}", feature, any_of.join(" or "))
+                    }
+                    None => format!("Features must contain {}", any_of.join(" or ")),
+                }))
+            }
+            FeatureRule::Conflicts { features, reason } => {
+                let conflicting: Vec<&String> = features.iter().filter(|f| requested(f)).collect();
+                if conflicting.len() < 2 {
+                    return None;
+                }
+
+                Some(
+                    reason
+                        .clone()
+                        .unwrap_or_else(|| match conflicting.as_slice() {
+                            [first, second] => {
+                                format!("Feature {} conflicts with {}", first, second)
+                            }
+                            _ => format!(
+                                "Features {} can't be used together",
+                                conflicting
+                                    .iter()
+                                    .map(|f| f.as_str())
+                                    .collect::<Vec<&str>>()
+                                    .join(", ")
+                            ),
+                        }),
+                )
+            }
+        }
+    }
+
+    // Features the rule names
+    fn features(&self) -> Vec<&String> {
+        match self {
+            FeatureRule::Requires {
+                feature, any_of, ..
+            } => feature.iter().chain(any_of).collect(),
+            FeatureRule::Conflicts { features, .. } => features.iter().collect(),
+        }
+    }
+}
+
+// FeatureRules is the table every request is validated against
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureRules {
+    pub rules: Vec<FeatureRule>,
+}
+
+impl FeatureRules {
+    // Loads the rules from the file in FEATURE_RULES_CONFIG, the rules of the feature catalog are used when it is not set
+    pub fn init() -> Self {
+        let config_path = match env::var("FEATURE_RULES_CONFIG") {
+            Ok(config_path) => config_path,
+            Err(_) => return Self::from_catalog(),
+        };
+
+        match Self::load_rules(&config_path) {
+            Ok(rules) => {
+                info!(target: "compiler", "{} feature rules loaded from {}", rules.len(), config_path);
+                FeatureRules { rules }
+            }
+            Err(e) => {
+                error!(target: "compiler", "Error loading feature rules from {}: {}", config_path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Rules of the feature catalog, a single standard and the standards and conflicts of every feature
+    pub fn from_catalog() -> Self {
+        let standards: Vec<String> = CONTRACTS.iter().map(|s| s.to_string()).collect();
+
+        let mut rules = vec![
+            FeatureRule::Requires {
+                feature: None,
+                any_of: standards.clone(),
+                reason: Some(String::from(
+                    "Features must contain at least one contract standard",
+                )),
+            },
+            FeatureRule::Conflicts {
+                features: standards,
+                reason: Some(String::from("Feature contains ambiguous contract standard")),
+            },
+        ];
+
+        for feature in FEATURES.iter() {
+            if !feature.standards.is_empty() {
+                rules.push(FeatureRule::Requires {
+                    feature: Some(feature.name.to_string()),
+                    any_of: feature.standards.iter().map(|s| s.to_string()).collect(),
+                    reason: None,
+                });
+            }
+
+            // Conflicts go both ways in the catalog, each pair gets a single rule
+            for conflict in feature.conflicts.iter().filter(|c| feature.name < **c) {
+                rules.push(FeatureRule::Conflicts {
+                    features: vec![feature.name.to_string(), conflict.to_string()],
+                    reason: None,
+                });
+            }
+        }
+
+        FeatureRules { rules }
+    }
+
+    // Reads the rules from a JSON file, every feature they name must be in the catalog
+    pub fn load_rules(config_path: &str) -> Result<Vec<FeatureRule>, String> {
+        let config = read_to_string(config_path).map_err(|e| e.to_string())?;
+        let rules: Vec<FeatureRule> = serde_json::from_str(&config).map_err(|e| e.to_string())?;
+
+        if let Some(unknown) = rules
+            .iter()
+            .flat_map(FeatureRule::features)
+            .find(|feature| find_feature(feature).is_none())
+        {
+            return Err(format!("Unknown feature in rules: {}", unknown));
+        }
+
+        Ok(rules)
+    }
+
+    // Reasons of every rule the features violate, empty when they follow all of them
+    pub fn violations(&self, features: &[String]) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.violation(features))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/feature_rules_tests.rs"]
+mod feature_rules_tests;
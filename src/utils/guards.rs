@@ -0,0 +1,41 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use std::env;
+
+// Request guard that only succeeds when the client asked for an async compilation (?async=true)
+// Otherwise the request is forwarded to the blocking /contract route
+pub struct AsyncCompilation;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AsyncCompilation {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.query_value::<bool>("async") {
+            Some(Ok(true)) => Outcome::Success(AsyncCompilation),
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
+
+// Request guard telling if the request carries the token set in the ADMIN_TOKEN env variable
+// in the X-Admin-Token header, it never fails so requests without it are handled as regular ones
+pub struct AdminAccess(pub bool);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminAccess {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let admin = match (
+            env::var("ADMIN_TOKEN"),
+            request.headers().get_one("X-Admin-Token"),
+        ) {
+            (Ok(admin_token), Some(token)) => !admin_token.is_empty() && admin_token == token,
+            _ => false,
+        };
+
+        Outcome::Success(AdminAccess(admin))
+    }
+}
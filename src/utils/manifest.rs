@@ -0,0 +1,48 @@
+use super::sanity_check::find_feature;
+use sha2::{Digest, Sha256};
+
+// Name of the Cargo.toml template shipped in compilation_target
+pub const MANIFEST_TEMPLATE: &str = "template-Cargo.toml";
+
+// Placeholder replaced by the openbrush features of each compilation
+pub const FEATURES_PLACEHOLDER: &str = "{{openbrush_features}}";
+
+// Features the init contract (template-lib.rs) is built with
+pub const TEMPLATE_FEATURES: [&str; 2] = ["psp22", "ownable"];
+
+// Maps the requested features to the openbrush features that have to be enabled
+// Features are sorted and deduplicated so the same request always renders the same manifest
+pub fn openbrush_features(features: &[String]) -> Vec<&'static str> {
+    let mut openbrush_features: Vec<&'static str> = features
+        .iter()
+        .filter_map(|feature| find_feature(feature).and_then(|feature| feature.openbrush_feature))
+        .collect();
+
+    openbrush_features.sort_unstable();
+    openbrush_features.dedup();
+
+    openbrush_features
+}
+
+// Renders the Cargo.toml of a contract enabling only the requested openbrush features
+pub fn render_manifest(template: &str, features: &[String]) -> String {
+    let rendered_features = openbrush_features(features)
+        .iter()
+        .map(|feature| format!("\"{}\"", feature))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    template.replace(FEATURES_PLACEHOLDER, &rendered_features)
+}
+
+// Short key identifying a rendered manifest, every feature set gets its own build cache
+pub fn manifest_key(manifest: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(manifest);
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..16].to_string()
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/manifest_tests.rs"]
+mod manifest_tests;
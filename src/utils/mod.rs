@@ -1,5 +1,17 @@
+pub mod build_key;
+pub mod codegen;
 pub mod common;
 pub mod compilation_queue;
 pub mod compiler;
 pub mod cors;
+pub mod diagnostics;
+pub mod fair_queue;
+pub mod feature_rules;
+pub mod guards;
+pub mod manifest;
+pub mod process;
+pub mod sandbox;
 pub mod sanity_check;
+pub mod test_report;
+pub mod toolchains;
+pub mod vendor;
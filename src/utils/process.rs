@@ -0,0 +1,117 @@
+use log::error;
+use std::fmt;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Interval used to check if the process finished
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Reasons a process could not run to completion
+#[derive(Debug)]
+pub enum RunError {
+    Io(std::io::Error),
+    TimedOut,
+    Cancelled,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Io(error) => write!(f, "{}", error),
+            RunError::TimedOut => write!(f, "process timed out"),
+            RunError::Cancelled => write!(f, "process cancelled"),
+        }
+    }
+}
+
+impl From<std::io::Error> for RunError {
+    fn from(error: std::io::Error) -> Self {
+        RunError::Io(error)
+    }
+}
+
+// Runs the command capturing stdout and stderr, killing it when the timeout is reached
+// or as soon as the cancelled flag is set
+// The command runs in its own process group so everything it spawned is killed along with it
+pub fn run_cancellable(
+    command: &mut Command,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<Output, RunError> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+
+    // Pipes are drained from separate threads so a chatty process never blocks on a full pipe
+    let stdout_reader = spawn_reader(child.stdout.take());
+    let stderr_reader = spawn_reader(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            join_reader(stdout_reader);
+            join_reader(stderr_reader);
+            return Err(RunError::TimedOut);
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            kill_process_group(&mut child);
+            join_reader(stdout_reader);
+            join_reader(stderr_reader);
+            return Err(RunError::Cancelled);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(Output {
+        status,
+        stdout: join_reader(stdout_reader),
+        stderr: join_reader(stderr_reader),
+    })
+}
+
+// Kills the whole process group of the child and reaps it
+fn kill_process_group(child: &mut Child) {
+    // The process group id is the pid of the child because of process_group(0)
+    let res = unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+    if res != 0 {
+        error!(target: "compiler", "Error killing process group {}", child.id());
+        let _ = child.kill();
+    }
+
+    if child.wait().is_err() {
+        error!(target: "compiler", "Error waiting for killed process {}", child.id());
+    }
+}
+
+fn spawn_reader<R: Read + Send + 'static>(pipe: Option<R>) -> Option<JoinHandle<Vec<u8>>> {
+    pipe.map(|mut pipe| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = pipe.read_to_end(&mut buffer);
+            buffer
+        })
+    })
+}
+
+fn join_reader(reader: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    reader
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/process_tests.rs"]
+mod process_tests;
@@ -0,0 +1,266 @@
+use log::{error, info};
+use std::env;
+use std::fs::{read_dir, read_link};
+use std::io;
+use std::os::unix::fs::lchown;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Default resource limits of every sandboxed process, 0 means unlimited
+pub const DEFAULT_MEMORY_LIMIT_MB: u64 = 8192;
+pub const DEFAULT_CPU_TIME_LIMIT: u64 = 600;
+pub const DEFAULT_MAX_PROCESSES: u64 = 512;
+
+// First uid the builds run as when SANDBOX_UID_BASE is not set, worker N builds as this uid + N
+pub const DEFAULT_UID_BASE: u32 = 10000;
+
+// Only these variables of the server environment reach the sandboxed processes
+// The rest, like MONGOURI and ADMIN_TOKEN, could be read by the contract and sent back in its output
+pub const ALLOWED_ENV_VARS: [&str; 6] = [
+    "PATH",
+    "HOME",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_TARGET_DIR",
+];
+
+// System dirs bound read-only, the ones missing in the host are skipped
+// /bin is only needed for the /bin/sh build scripts run
+const SYSTEM_DIRS: [&str; 6] = ["/usr", "/bin", "/lib", "/lib32", "/lib64", "/etc/ssl"];
+
+// Sandbox used to run the builds of untrusted contracts
+// Processes run inside bubblewrap with no network, read-only access to the system and toolchain dirs only and
+// the workspace as the only writable directory, limited by rlimits
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+    pub enabled: bool,
+    pub bwrap: String,
+    pub memory_limit_mb: u64,
+    pub cpu_time_limit: u64,
+    pub max_processes: u64,
+    // First of the uids the workers build as, 0 builds as the server user
+    pub uid_base: u32,
+    // Unprivileged uid the builds of a worker run as, only one build runs under it at a time
+    pub build_uid: Option<u32>,
+}
+
+// Reads a resource limit from the environment
+fn limit_from_env(name: &str, default: u64) -> u64 {
+    match env::var(name) {
+        Ok(v) => match v.parse::<u64>() {
+            Ok(limit) => limit,
+            Err(_) => {
+                error!(target: "compiler", "Invalid {} value: {}", name, v);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+// Sandbox implementation
+impl Sandbox {
+    // Creates the sandbox from env variables and checks bubblewrap works in this host
+    // The server still starts when it doesn't, but builds fail until it is fixed
+    pub fn init() -> Self {
+        let sandbox = Sandbox {
+            enabled: env::var("SANDBOX_ENABLED").map_or(true, |v| v != "false"),
+            bwrap: env::var("SANDBOX_BWRAP").unwrap_or(String::from("bwrap")),
+            memory_limit_mb: limit_from_env("SANDBOX_MEMORY_LIMIT_MB", DEFAULT_MEMORY_LIMIT_MB),
+            cpu_time_limit: limit_from_env("SANDBOX_CPU_TIME_LIMIT", DEFAULT_CPU_TIME_LIMIT),
+            max_processes: limit_from_env("SANDBOX_MAX_PROCESSES", DEFAULT_MAX_PROCESSES),
+            uid_base: u32::try_from(limit_from_env("SANDBOX_UID_BASE", DEFAULT_UID_BASE as u64))
+                .unwrap_or(DEFAULT_UID_BASE),
+            build_uid: None,
+        };
+
+        // The process limit counts every process of a uid and doesn't apply to root
+        if sandbox.max_processes > 0 && !sandbox.switches_uid() {
+            error!(target: "compiler", "SANDBOX_MAX_PROCESSES is only enforced when the server runs as root with SANDBOX_UID_BASE set");
+        }
+
+        if !sandbox.enabled {
+            error!(target: "compiler", "Sandbox disabled, contracts are built with the server privileges");
+            return sandbox;
+        }
+
+        // Run a no-op inside the sandbox to make sure namespaces are available
+        let check = sandbox
+            .wrap(Command::new("true"), &env::temp_dir(), &[])
+            .status();
+
+        match check {
+            Ok(status) if status.success() => {
+                info!(target: "compiler", "Sandbox initialized");
+                sandbox
+            }
+            _ => {
+                error!(target: "compiler", "Error running {}, builds will fail until bubblewrap can create namespaces in this host or SANDBOX_ENABLED=false is set", sandbox.bwrap);
+                sandbox
+            }
+        }
+    }
+
+    // Sandbox of a compiler worker, whose builds run as its own uid when the server can switch to it
+    pub fn for_worker(&self, worker_id: usize) -> Sandbox {
+        Sandbox {
+            build_uid: self
+                .switches_uid()
+                .then(|| self.uid_base + worker_id as u32),
+            ..self.clone()
+        }
+    }
+
+    // Only root can run the builds as another uid
+    fn switches_uid(&self) -> bool {
+        self.uid_base > 0 && unsafe { libc::geteuid() } == 0
+    }
+
+    // Hands a workspace over to the build uid, so the build can write in it
+    // Only the entries right under it are changed unless recursive is set, the build owns what it creates
+    // Links are never followed, a build could point them anywhere
+    pub fn give_workspace(&self, workspace: &Path, recursive: bool) -> io::Result<()> {
+        let uid = match self.build_uid {
+            Some(uid) => uid,
+            None => return Ok(()),
+        };
+
+        lchown(workspace, Some(uid), Some(uid))?;
+        for entry in read_dir(workspace)? {
+            let entry = entry?;
+            if recursive && entry.file_type()?.is_dir() {
+                self.give_workspace(&entry.path(), true)?;
+            } else {
+                lchown(entry.path(), Some(uid), Some(uid))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Wraps a command so it runs inside the sandbox with the workspace as its only writable dir
+    // Besides the system and toolchain dirs, only the read_only dirs are visible
+    // The working dir and the variables set on the command are kept, the server environment is
+    // cleared but for ALLOWED_ENV_VARS
+    // bubblewrap runs the command as PID 1 of a new PID namespace, so killing bubblewrap
+    // takes down every process the build spawned
+    pub fn wrap(&self, command: Command, workspace: &Path, read_only: &[PathBuf]) -> Command {
+        let mut wrapped = if self.enabled {
+            let mut wrapped = Command::new(&self.bwrap);
+            wrapped.args(["--unshare-all", "--die-with-parent", "--new-session"]);
+
+            for dir in SYSTEM_DIRS.iter().map(Path::new) {
+                // Merged /usr hosts have /bin and /lib as links into /usr
+                match read_link(dir) {
+                    Ok(target) => wrapped.arg("--symlink").arg(target).arg(dir),
+                    Err(_) => wrapped.arg("--ro-bind-try").arg(dir).arg(dir),
+                };
+            }
+
+            for dir in toolchain_dirs().iter().chain(read_only) {
+                wrapped.arg("--ro-bind-try").arg(dir).arg(dir);
+            }
+
+            wrapped
+                .args(["--dev", "/dev"])
+                .args(["--proc", "/proc"])
+                .args(["--tmpfs", "/tmp"])
+                .arg("--bind")
+                .arg(workspace)
+                .arg(workspace)
+                .arg("--chdir")
+                .arg(command.get_current_dir().unwrap_or(workspace))
+                .arg("--")
+                .arg(command.get_program())
+                .args(command.get_args());
+            wrapped
+        } else {
+            let mut wrapped = Command::new(command.get_program());
+            wrapped.args(command.get_args());
+            wrapped
+        };
+
+        wrapped.env_clear();
+        for key in ALLOWED_ENV_VARS {
+            if let Ok(value) = env::var(key) {
+                wrapped.env(key, value);
+            }
+        }
+
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => wrapped.env(key, value),
+                None => wrapped.env_remove(key),
+            };
+        }
+
+        if let Some(current_dir) = command.get_current_dir() {
+            wrapped.current_dir(current_dir);
+        }
+
+        // The process limit counts every process of the uid, so it is only set when the uid runs nothing but this build
+        let build_uid = self.build_uid;
+        let limits = [
+            (libc::RLIMIT_AS, self.memory_limit_mb * 1024 * 1024),
+            (libc::RLIMIT_CPU, self.cpu_time_limit),
+            (
+                libc::RLIMIT_NPROC,
+                build_uid.map_or(0, |_| self.max_processes),
+            ),
+        ];
+
+        // The limits and the uid are set in the child right before exec, so they are inherited by
+        // bubblewrap and everything cargo spawns
+        unsafe {
+            wrapped.pre_exec(move || {
+                for (resource, limit) in limits {
+                    if limit == 0 {
+                        continue;
+                    }
+
+                    let rlimit = libc::rlimit {
+                        rlim_cur: limit,
+                        rlim_max: limit,
+                    };
+                    if libc::setrlimit(resource, &rlimit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+
+                if let Some(uid) = build_uid {
+                    if libc::setgroups(0, std::ptr::null()) != 0
+                        || libc::setgid(uid) != 0
+                        || libc::setuid(uid) != 0
+                    {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+
+        wrapped
+    }
+}
+
+// Cargo and rustup homes, where the toolchains and the fetched crates are
+fn toolchain_dirs() -> Vec<PathBuf> {
+    let home = env::var("HOME").map(PathBuf::from).ok();
+    let dir = |name: &str, default: &str| {
+        env::var(name)
+            .map(PathBuf::from)
+            .ok()
+            .or(home.as_ref().map(|home| home.join(default)))
+    };
+
+    dir("CARGO_HOME", ".cargo")
+        .into_iter()
+        .chain(dir("RUSTUP_HOME", ".rustup"))
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/sandbox_tests.rs"]
+mod sandbox_tests;
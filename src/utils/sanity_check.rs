@@ -1,25 +1,79 @@
-use crate::models::api_models::{ServerResponse, WizardMessage};
-use crate::models::db_models::Contract;
+use crate::models::api_models::{BuildOptions, ServerError, ServerResponse, WizardMessage};
+use crate::models::db_models::JobPriority;
+use crate::utils::feature_rules::FeatureRules;
+use crate::utils::toolchains::{ToolchainProfile, Toolchains};
 use log::error;
 use rocket::{http::Status, response::status::Custom, serde::json::Json};
 use sp_core::crypto::{AccountId32, Ss58Codec};
 
 pub const CONTRACTS: [&str; 3] = ["psp22", "psp34", "psp37"];
 
-pub const ALLOWED_FEATURES: [&str; 6] = [
-    "psp22",
-    "psp34",
-    "psp37",
-    "pausable",
-    "ownable",
-    "access-control",
+// A feature contracts can be built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feature {
+    pub name: &'static str,
+    // Openbrush feature enabled in the contract Cargo.toml, extensions of a standard ship with it
+    pub openbrush_feature: Option<&'static str>,
+    // Standards the feature extends, empty when it can be used with any of them
+    pub standards: &'static [&'static str],
+    // Features that can't be used along with this one
+    pub conflicts: &'static [&'static str],
+}
+
+const fn feature(
+    name: &'static str,
+    openbrush_feature: Option<&'static str>,
+    standards: &'static [&'static str],
+    conflicts: &'static [&'static str],
+) -> Feature {
+    Feature {
+        name,
+        openbrush_feature,
+        standards,
+        conflicts,
+    }
+}
+
+// Every feature accepted in a request, the standards first
+pub const FEATURES: [Feature; 19] = [
+    feature("psp22", Some("psp22"), &[], &[]),
+    feature("psp34", Some("psp34"), &[], &[]),
+    feature("psp37", Some("psp37"), &[], &[]),
+    feature("metadata", None, &["psp22", "psp34", "psp37"], &[]),
+    feature("mintable", None, &["psp22", "psp34", "psp37"], &["wrapper"]),
+    feature("burnable", None, &["psp22", "psp34", "psp37"], &[]),
+    feature("capped", None, &["psp22"], &[]),
+    // Flash loans and wrapped deposits mint tokens that are not backed by the supply rules
+    feature("flashmint", None, &["psp22"], &["wrapper"]),
+    feature("wrapper", None, &["psp22"], &["mintable", "flashmint"]),
+    feature("enumerable", None, &["psp34", "psp37"], &[]),
+    feature("batch", None, &["psp37"], &[]),
+    feature("pausable", Some("pausable"), &[], &[]),
+    feature("ownable", Some("ownable"), &[], &[]),
+    feature("access-control", Some("access_control"), &[], &[]),
+    feature("timelock", Some("timelock_controller"), &[], &[]),
+    feature("reentrancy-guard", Some("reentrancy_guard"), &[], &[]),
+    feature("upgradeable", Some("upgradeable"), &[], &[]),
+    // Proxies and diamonds both forward calls to other code, a contract is one or the other
+    feature("proxy", Some("proxy"), &[], &["diamond"]),
+    feature("diamond", Some("diamond"), &[], &["proxy"]),
 ];
 
+// Looks up a feature in the catalog
+pub fn find_feature(name: &str) -> Option<&'static Feature> {
+    FEATURES.iter().find(|feature| feature.name == name)
+}
+
 pub const MAX_SIZE_ALLOWED: usize = 49999;
 
-pub fn sanity_check_wizard_message(
+// Optimization levels accepted by wasm-opt
+pub const OPTIMIZATION_PASSES: [&str; 7] = ["0", "1", "2", "3", "4", "s", "z"];
+
+pub fn sanity_check_wizard_message<T>(
     wizard_message: &Json<WizardMessage>,
-) -> Result<(), Custom<Json<ServerResponse<Contract>>>> {
+    profile: &ToolchainProfile,
+    rules: &FeatureRules,
+) -> Result<(), Custom<Json<ServerResponse<T>>>> {
     // Checks length of the code not passing the max allowed
     match check_code_len(&wizard_message.code) {
         Ok(_) => (),
@@ -42,7 +96,21 @@ pub fn sanity_check_wizard_message(
         }
     }
 
-    check_features(&wizard_message.features)?;
+    // Features only pick openbrush modules, profiles without openbrush build the code as it is
+    if profile.openbrush_version.is_some() {
+        check_features(&wizard_message.features, rules)?;
+    }
+
+    // Checks the build options are valid
+    match check_build_options(&wizard_message.build_options) {
+        Ok(_) => (),
+        Err(msg) => {
+            return Err(Custom(
+                Status::InternalServerError,
+                Json(ServerResponse::new_error(msg)),
+            ))
+        }
+    }
 
     Ok(())
 }
@@ -65,9 +133,64 @@ pub fn check_address(address: &String) -> Result<(), String> {
     Ok(())
 }
 
-pub fn check_features(
+pub fn check_build_options(options: &BuildOptions) -> Result<(), String> {
+    if let Some(passes) = &options.optimization_passes {
+        if !OPTIMIZATION_PASSES.contains(&passes.as_str()) {
+            error!(target: "compiler", "Optimization passes not allowed: {:?}", passes);
+            return Err("Optimization passes not allowed.".to_string());
+        }
+    }
+
+    // Crate features end up in the cargo command line, so only plain feature names are accepted
+    for feature in &options.features {
+        let valid = !feature.is_empty()
+            && !feature.starts_with('-')
+            && feature
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            error!(target: "compiler", "Build feature not allowed: {:?}", feature);
+            return Err("Build feature not allowed.".to_string());
+        }
+    }
+    Ok(())
+}
+
+pub fn check_priority<T>(
+    priority: &Option<JobPriority>,
+    admin: bool,
+) -> Result<(), Custom<Json<ServerResponse<T>>>> {
+    // Only admins can put their jobs ahead of everyone else
+    if *priority == Some(JobPriority::High) && !admin {
+        error!(target: "compiler", "High priority requested without the admin token");
+        return Err(Custom(
+            Status::Forbidden,
+            Json(ServerResponse::new_error(String::from(
+                "High priority is reserved for admins.",
+            ))),
+        ));
+    }
+    Ok(())
+}
+
+pub fn check_toolchain<'a, T>(
+    toolchains: &'a Toolchains,
+    toolchain: &Option<String>,
+) -> Result<&'a ToolchainProfile, Custom<Json<ServerResponse<T>>>> {
+    // Checks the requested toolchain profile is installed
+    toolchains.get(toolchain.as_deref()).map_err(|msg| {
+        error!(target: "compiler", "{}", msg);
+        Custom(
+            Status::InternalServerError,
+            Json(ServerResponse::new_error(msg)),
+        )
+    })
+}
+
+pub fn check_features<T>(
     features: &Vec<String>,
-) -> Result<(), Custom<Json<ServerResponse<Contract>>>> {
+    rules: &FeatureRules,
+) -> Result<(), Custom<Json<ServerResponse<T>>>> {
     // Checks features not to be empty
     if features.is_empty() {
         error!(target: "compiler", "Features are empty");
@@ -79,45 +202,20 @@ pub fn check_features(
         ));
     }
 
-    // Checks all the features passed are allowed
-    for feature in features {
-        if !ALLOWED_FEATURES.contains(&feature.as_str()) {
-            error!(target: "compiler", "Feature not allowed: {:?}", feature);
-            return Err(Custom(
-                Status::InternalServerError,
-                Json(ServerResponse::new_error(String::from(
-                    "Feature not allowed",
-                ))),
-            ));
-        }
-    }
+    // Every unknown feature and every violated rule are reported at once
+    let mut violations: Vec<String> = features
+        .iter()
+        .filter(|feature| find_feature(feature).is_none())
+        .map(|feature| format!("Feature not allowed: {}", feature))
+        .collect();
+    violations.extend(rules.violations(features));
 
-    // sets the found flag
-    let mut found = false;
-
-    // found flag is used to check the contract has a single and allowed standard
-    for feature in features {
-        if CONTRACTS.contains(&feature.as_str()) {
-            if !found {
-                found = true;
-            } else {
-                error!(target: "compiler", "Feature contains ambiguous contract standard");
-                return Err(Custom(
-                    Status::InternalServerError,
-                    Json(ServerResponse::new_error(String::from(
-                        "Feature contains ambiguous contract standard",
-                    ))),
-                ));
-            }
-        }
-    }
-    // here it checks at least one standard was found
-    if !found {
-        error!(target: "compiler", "Features must contain at least one contract standard");
+    if !violations.is_empty() {
+        error!(target: "compiler", "Features {:?} violate the feature rules: {:?}", features, violations);
         return Err(Custom(
             Status::InternalServerError,
-            Json(ServerResponse::new_error(String::from(
-                "Features must contain at least one contract standard",
+            Json(ServerResponse::from_error(ServerError::with_violations(
+                violations,
             ))),
         ));
     }
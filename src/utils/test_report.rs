@@ -0,0 +1,62 @@
+use crate::models::api_models::{TestCase, TestOutcome, TestReport};
+
+// Parses the output of cargo test run with --show-output into a per-test report
+// Every test is listed by its result line, the captured output comes in a block per test
+pub fn parse_test_output(output: &str) -> TestReport {
+    let mut tests: Vec<TestCase> = vec![];
+    // Test whose captured output is being read
+    let mut current: Option<usize> = None;
+
+    for line in output.lines() {
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|line| line.strip_suffix(" stdout ----"))
+        {
+            current = tests.iter().position(|test| test.name == name);
+            continue;
+        }
+
+        if line == "successes:" || line == "failures:" || line.starts_with("test result:") {
+            current = None;
+            continue;
+        }
+
+        if let Some(index) = current {
+            tests[index].output.push_str(line);
+            tests[index].output.push('\n');
+            continue;
+        }
+
+        if let Some(test) = parse_result_line(line) {
+            tests.push(test);
+        }
+    }
+
+    for test in tests.iter_mut() {
+        test.output = test.output.trim_end().to_string();
+    }
+
+    TestReport::new(tests)
+}
+
+// Parses lines like "test my_contract::tests::it_works ... ok"
+fn parse_result_line(line: &str) -> Option<TestCase> {
+    let (name, result) = line.strip_prefix("test ")?.split_once(" ... ")?;
+
+    let outcome = match result {
+        "ok" => TestOutcome::Passed,
+        "FAILED" => TestOutcome::Failed,
+        result if result.starts_with("ignored") => TestOutcome::Ignored,
+        _ => return None,
+    };
+
+    Some(TestCase {
+        name: name.to_string(),
+        outcome,
+        output: String::new(),
+    })
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/test_report_tests.rs"]
+mod test_report_tests;
@@ -0,0 +1,237 @@
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::read_to_string;
+use std::path::{absolute, Path, PathBuf};
+use std::process::Command;
+
+use super::manifest::{render_manifest, MANIFEST_TEMPLATE};
+use super::vendor::{missing_crates, VENDOR_CONFIG, VENDOR_LOCKFILE};
+
+// Config file listing the toolchain profiles, it can be changed with TOOLCHAINS_CONFIG
+pub const DEFAULT_TOOLCHAINS_CONFIG: &str = "toolchains.json";
+
+// A toolchain profile describes how contracts for an ink! version are built
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolchainProfile {
+    pub name: String,
+    pub description: String,
+    pub ink_version: String,
+    pub openbrush_version: Option<String>,
+    pub rust_toolchain: String,
+    #[serde(default)]
+    pub default: bool,
+    // Directory holding the template-Cargo.toml and template-lib.rs of the profile
+    #[serde(skip_serializing)]
+    pub template_dir: String,
+    // Cargo binary used by the profile, the CARGO env variable is used when missing
+    #[serde(default, skip_serializing)]
+    pub cargo: Option<String>,
+    // Directory prepended to PATH so cargo picks the cargo-contract binary of the profile
+    #[serde(default, skip_serializing)]
+    pub bin_path: Option<String>,
+    // Directory with the crates vendored by scripts/vendor.sh, builds run fully offline when set
+    #[serde(default, skip_serializing)]
+    pub vendor_dir: Option<String>,
+    // Runs the ink! linter in lint mode, needs cargo-dylint and the ink_linting library installed
+    #[serde(default)]
+    pub ink_linting: bool,
+    // Filled in at startup with the output of cargo contract --version
+    #[serde(default)]
+    pub cargo_contract_version: Option<String>,
+}
+
+impl ToolchainProfile {
+    // Creates a cargo command that runs with the rustup toolchain of the profile
+    pub fn cargo_command(&self) -> Command {
+        let mut command = Command::new(self.cargo.clone().unwrap_or(String::from("cargo")));
+        command.env("RUSTUP_TOOLCHAIN", &self.rust_toolchain);
+
+        if let Some(bin_path) = &self.bin_path {
+            let path = env::var("PATH").unwrap_or_default();
+            command.env("PATH", format!("{}:{}", bin_path, path));
+        }
+
+        command
+    }
+
+    // Dirs of the profile its builds read besides the system and toolchain ones, bound read-only in the sandbox
+    pub fn sandbox_dirs(&self) -> Vec<PathBuf> {
+        self.bin_path
+            .iter()
+            .chain(&self.vendor_dir)
+            .filter_map(|dir| absolute(dir).ok())
+            .collect()
+    }
+
+    // Renders the Cargo.toml a contract with the given features is built with
+    pub fn render_manifest(&self, features: &[String]) -> std::io::Result<String> {
+        let template = read_to_string(Path::new(&self.template_dir).join(MANIFEST_TEMPLATE))?;
+        Ok(render_manifest(&template, features))
+    }
+
+    // Checks the profile can be used, returning the cargo-contract version installed
+    fn check_installed(&self) -> Result<String, String> {
+        if self.cargo.is_none() {
+            return Err(String::from("CARGO environment variable not set"));
+        }
+
+        if !Path::new(&self.template_dir).is_dir() {
+            return Err(format!("Template dir {} not found", self.template_dir));
+        }
+
+        // Without this check cargo would silently fall back to the cargo-contract found in PATH
+        if let Some(bin_path) = &self.bin_path {
+            if !Path::new(bin_path).join("cargo-contract").is_file() {
+                return Err(format!("cargo-contract not found in {}", bin_path));
+            }
+        }
+
+        let output = self
+            .cargo_command()
+            .arg("contract")
+            .arg("--version")
+            .output()
+            .map_err(|e| format!("Error running cargo: {:?}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "Toolchain {} or cargo-contract not installed: {}",
+                self.rust_toolchain,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        if let Some(vendor_dir) = &self.vendor_dir {
+            self.check_vendored(Path::new(vendor_dir))?;
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    // Checks every crate locked for the profile is in the vendor dir, listing the missing ones
+    fn check_vendored(&self, vendor_dir: &Path) -> Result<(), String> {
+        if !vendor_dir.join(VENDOR_CONFIG).is_file() {
+            return Err(format!(
+                "{} not found in vendor dir {}",
+                VENDOR_CONFIG,
+                vendor_dir.display()
+            ));
+        }
+
+        let lockfile = read_to_string(vendor_dir.join(VENDOR_LOCKFILE)).map_err(|e| {
+            format!(
+                "Error reading {} from vendor dir {}: {}",
+                VENDOR_LOCKFILE,
+                vendor_dir.display(),
+                e
+            )
+        })?;
+
+        let missing = missing_crates(&lockfile, vendor_dir)
+            .map_err(|e| format!("Error parsing {}: {}", VENDOR_LOCKFILE, e))?;
+
+        if !missing.is_empty() {
+            return Err(format!(
+                "{} crates missing from vendor dir {}: {}",
+                missing.len(),
+                vendor_dir.display(),
+                missing.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Toolchains holds the profiles that are installed in this host
+pub struct Toolchains {
+    pub profiles: Vec<ToolchainProfile>,
+}
+
+// Toolchains implementation
+impl Toolchains {
+    // Loads the profiles from the config file and keeps the installed ones
+    pub fn init() -> Self {
+        let config_path =
+            env::var("TOOLCHAINS_CONFIG").unwrap_or(String::from(DEFAULT_TOOLCHAINS_CONFIG));
+
+        let profiles = match Self::load_profiles(&config_path) {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                error!(target: "compiler", "Error loading toolchains from {}: {:?}", config_path, e);
+                std::process::exit(1);
+            }
+        };
+
+        let default_cargo = env::var("CARGO").ok();
+
+        let installed: Vec<ToolchainProfile> = profiles
+            .into_iter()
+            .filter_map(|mut profile| {
+                if profile.cargo.is_none() {
+                    profile.cargo = default_cargo.clone();
+                }
+
+                match profile.check_installed() {
+                    Ok(version) => {
+                        info!(target: "compiler", "Toolchain profile {} installed: {}", profile.name, version);
+                        profile.cargo_contract_version = Some(version);
+                        Some(profile)
+                    }
+                    Err(e) => {
+                        error!(target: "compiler", "Toolchain profile {} not available: {}", profile.name, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        // The server still starts so the endpoints that don't build contracts keep working
+        if installed.is_empty() {
+            error!(target: "compiler", "No toolchain profile installed, contracts can't be built");
+        }
+
+        Toolchains::new(installed)
+    }
+
+    // Creates the toolchains from already checked profiles
+    pub fn new(profiles: Vec<ToolchainProfile>) -> Self {
+        Toolchains { profiles }
+    }
+
+    // Reads the toolchain profiles from a JSON file
+    pub fn load_profiles(
+        config_path: &str,
+    ) -> Result<Vec<ToolchainProfile>, Box<dyn std::error::Error>> {
+        let config = read_to_string(config_path)?;
+        let profiles: Vec<ToolchainProfile> = serde_json::from_str(&config)?;
+        Ok(profiles)
+    }
+
+    // Returns the profile marked as default or the first one, None when no profile is installed
+    pub fn default_profile(&self) -> Option<&ToolchainProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.default)
+            .or(self.profiles.first())
+    }
+
+    // Returns the requested profile or the default one when none was requested
+    pub fn get(&self, name: Option<&str>) -> Result<&ToolchainProfile, String> {
+        match name {
+            None => self
+                .default_profile()
+                .ok_or(String::from("No toolchain installed")),
+            Some(name) => self
+                .profiles
+                .iter()
+                .find(|profile| profile.name == name)
+                .ok_or(format!("Toolchain not available: {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/toolchains_tests.rs"]
+mod toolchains_tests;
@@ -0,0 +1,92 @@
+use serde::Deserialize;
+use std::path::Path;
+use toml::{Table, Value};
+
+// Files written by scripts/vendor.sh next to the vendored crates
+pub const VENDOR_CONFIG: &str = "config.toml";
+pub const VENDOR_LOCKFILE: &str = "Cargo.lock";
+
+// Cargo.lock of the vendored dependency set
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+// A package pinned in Cargo.lock, packages without source belong to the contract crate
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub source: Option<String>,
+}
+
+// Manifest of a vendored crate, only its version is needed
+#[derive(Debug, Deserialize)]
+struct VendoredManifest {
+    package: VendoredPackage,
+}
+
+#[derive(Debug, Deserialize)]
+struct VendoredPackage {
+    version: String,
+}
+
+// Returns the "name version" of every locked crate that is not in the vendor dir
+pub fn missing_crates(lockfile: &str, vendor_dir: &Path) -> Result<Vec<String>, toml::de::Error> {
+    let lockfile: Lockfile = toml::from_str(lockfile)?;
+
+    Ok(lockfile
+        .package
+        .iter()
+        .filter(|package| package.source.is_some() && !is_vendored(package, vendor_dir))
+        .map(|package| format!("{} {}", package.name, package.version))
+        .collect())
+}
+
+// cargo vendor stores a crate in <name>-<version> with --versioned-dirs and in <name> otherwise
+fn is_vendored(package: &LockedPackage, vendor_dir: &Path) -> bool {
+    if vendor_dir
+        .join(format!("{}-{}", package.name, package.version))
+        .is_dir()
+    {
+        return true;
+    }
+
+    std::fs::read_to_string(vendor_dir.join(&package.name).join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| toml::from_str::<VendoredManifest>(&manifest).ok())
+        .is_some_and(|manifest| manifest.package.version == package.version)
+}
+
+// Renders the .cargo/config.toml of a workspace from the config printed by cargo vendor
+// Vendored sources point to the vendor dir and cargo is never allowed to reach the network
+pub fn render_cargo_config(
+    vendor_config: &str,
+    vendor_dir: &Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut config: Table = toml::from_str(vendor_config)?;
+
+    if let Some(Value::Table(sources)) = config.get_mut("source") {
+        for (_, source) in sources.iter_mut() {
+            if let Value::Table(source) = source {
+                if source.contains_key("directory") {
+                    source.insert(
+                        String::from("directory"),
+                        Value::String(vendor_dir.display().to_string()),
+                    );
+                }
+            }
+        }
+    }
+
+    let mut net = Table::new();
+    net.insert(String::from("offline"), Value::Boolean(true));
+    config.insert(String::from("net"), Value::Table(net));
+
+    Ok(toml::to_string(&config)?)
+}
+
+#[cfg(test)]
+#[path = "../tests/utils/vendor_tests.rs"]
+mod vendor_tests;